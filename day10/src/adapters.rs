@@ -0,0 +1,234 @@
+// adapters.rs: chains of adapters under configurable joltage rules. An
+// adapter can follow another if the difference in joltage is one of the
+// allowed gaps. Chains start at the outlet (0 jolts) and end at the device,
+// which is rated for the largest gap above the highest adapter.
+
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+use std::collections::BTreeMap;
+
+pub struct Adapters {
+    // sorted, including the outlet and the device
+    joltages: Vec<i64>,
+    gaps: Vec<i64>,
+}
+
+impl Adapters {
+    pub fn new(adapters: &[i64], gaps: &[i64]) -> Result<Adapters, String> {
+        let mut gaps = gaps.to_vec();
+        gaps.sort_unstable();
+        gaps.dedup();
+        let max_gap = match gaps.last() {
+            Some(&gap) if gaps[0] > 0 => gap,
+            Some(_) => return Err("gaps must be positive".to_string()),
+            None => return Err("no gaps allowed".to_string()),
+        };
+        let mut joltages = adapters.to_vec();
+        if let Some(&j) = joltages.iter().find(|&&j| j <= 0) {
+            return Err(format!("bad adapter joltage: {}", j));
+        }
+        joltages.push(0);
+        joltages.sort_unstable();
+        joltages.push(joltages[joltages.len() - 1] + max_gap);
+        Ok(Adapters { joltages, gaps })
+    }
+
+    fn allowed(&self, from: usize, to: usize) -> bool {
+        let gap = self.joltages[to] - self.joltages[from];
+        self.gaps.binary_search(&gap).is_ok()
+    }
+
+    // The adapters that can directly follow the one at index from.
+    fn next(&self, from: usize) -> impl Iterator<Item = usize> + '_ {
+        let max_gap = self.gaps[self.gaps.len() - 1];
+        (from + 1..self.joltages.len())
+            .take_while(move |&to| {
+                self.joltages[to] - self.joltages[from] <= max_gap
+            })
+            .filter(move |&to| self.allowed(from, to))
+    }
+
+    // How many differences of each size there are in the chain that uses
+    // every adapter.
+    pub fn histogram(&self) -> BTreeMap<i64, usize> {
+        let mut hist = BTreeMap::new();
+        for w in self.joltages.windows(2) {
+            *hist.entry(w[1] - w[0]).or_insert(0) += 1;
+        }
+        hist
+    }
+
+    // Whether the chain that uses every adapter follows the rules.
+    pub fn uses_all(&self) -> bool {
+        (1..self.joltages.len()).all(|i| self.allowed(i - 1, i))
+    }
+
+    // For each adapter, how many valid chains lead from it to the device.
+    fn ways(&self) -> Vec<BigUint> {
+        let n = self.joltages.len();
+        let mut ways = vec![BigUint::zero(); n];
+        ways[n - 1] = BigUint::one();
+        for from in (0..n - 1).rev() {
+            let mut sum = BigUint::zero();
+            for to in self.next(from) {
+                sum += &ways[to];
+            }
+            ways[from] = sum;
+        }
+        ways
+    }
+
+    pub fn arrangements(&self) -> BigUint {
+        self.ways().swap_remove(0)
+    }
+
+    // The next adapters that still lead to the device, in increasing order.
+    fn live_next(&self, from: usize, ways: &[BigUint]) -> Vec<usize> {
+        self.next(from).filter(|&to| !ways[to].is_zero()).collect()
+    }
+
+    fn chain(&self, path: &[usize]) -> Vec<i64> {
+        path.iter().map(|&i| self.joltages[i]).collect()
+    }
+
+    // Every valid chain in lexicographic order, from the outlet to the
+    // device. Only branches that reach the device are explored.
+    pub fn chains(&self) -> Chains<'_> {
+        let ways = self.ways();
+        let mut stack = Vec::new();
+        if !ways[0].is_zero() {
+            let mut next = self.live_next(0, &ways);
+            next.reverse();
+            stack.push((0, next));
+        }
+        Chains {
+            adapters: self,
+            ways,
+            stack,
+        }
+    }
+
+    // Picks a valid chain uniformly at random by taking each branch with
+    // probability proportional to the number of chains through it.
+    pub fn sample(&self, rng: &mut Rng) -> Option<Vec<i64>> {
+        let ways = self.ways();
+        if ways[0].is_zero() {
+            return None;
+        }
+        let mut path = vec![0];
+        let mut from = 0;
+        while from != self.joltages.len() - 1 {
+            let mut r = rng.below(&ways[from]);
+            for to in self.live_next(from, &ways) {
+                if r < ways[to] {
+                    from = to;
+                    break;
+                }
+                r -= &ways[to];
+            }
+            path.push(from);
+        }
+        Some(self.chain(&path))
+    }
+
+    // The most adapters that can be left out while still leaving a valid
+    // chain, which are the ones off a shortest chain. Returns None if there
+    // is no valid chain at all.
+    pub fn removable(&self) -> Option<Vec<i64>> {
+        let n = self.joltages.len();
+        // hops[i] is the length of the shortest chain from i to the device,
+        // and best[i] the adapter to take next to get it
+        let mut hops = vec![None; n];
+        let mut best = vec![0; n];
+        hops[n - 1] = Some(0);
+        for from in (0..n - 1).rev() {
+            for to in self.next(from) {
+                let h = match hops[to] {
+                    Some(h) => h + 1,
+                    None => continue,
+                };
+                if hops[from].is_none_or(|cur| h < cur) {
+                    hops[from] = Some(h);
+                    best[from] = to;
+                }
+            }
+        }
+        hops[0]?;
+        let mut keep = vec![false; n];
+        let mut i = 0;
+        while i != n - 1 {
+            keep[i] = true;
+            i = best[i];
+        }
+        keep[n - 1] = true;
+        let removed = (0..n).filter(|&i| !keep[i]).collect::<Vec<_>>();
+        Some(self.chain(&removed))
+    }
+}
+
+pub struct Chains<'a> {
+    adapters: &'a Adapters,
+    ways: Vec<BigUint>,
+    // the current path, with the adapters still to try after each one
+    stack: Vec<(usize, Vec<usize>)>,
+}
+
+impl Iterator for Chains<'_> {
+    type Item = Vec<i64>;
+
+    fn next(&mut self) -> Option<Vec<i64>> {
+        let device = self.adapters.joltages.len() - 1;
+        loop {
+            let (from, rest) = self.stack.last_mut()?;
+            if *from == device {
+                let path: Vec<_> = self.stack.iter().map(|f| f.0).collect();
+                self.stack.pop();
+                return Some(self.adapters.chain(&path));
+            }
+            match rest.pop() {
+                Some(to) => {
+                    let mut next = self.adapters.live_next(to, &self.ways);
+                    next.reverse();
+                    self.stack.push((to, next));
+                }
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
+
+// A xorshift generator: the same seed gives the same samples.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng(seed.max(1))
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 32) as u32
+    }
+
+    // A uniformly random number in 0..n, by rejecting numbers of the same
+    // bit length that are too big.
+    fn below(&mut self, n: &BigUint) -> BigUint {
+        let bits = n.bits();
+        let words = bits.div_ceil(32) as usize;
+        loop {
+            let mut digits: Vec<_> =
+                (0..words).map(|_| self.next_u32()).collect();
+            if !bits.is_multiple_of(32) {
+                digits[words - 1] &= (1 << (bits % 32)) - 1;
+            }
+            let r = BigUint::from_slice(&digits);
+            if &r < n {
+                return r;
+            }
+        }
+    }
+}
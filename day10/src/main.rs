@@ -1,53 +1,86 @@
-use std::collections::HashMap;
+mod adapters;
+
+use adapters::{Adapters, Rng};
 
 fn atoi(s: &str) -> i64 {
-    i64::from_str_radix(s, 10).unwrap()
+    s.parse().unwrap()
 }
 
-fn differences(chain: &[i64], of: i64) -> usize {
-    let mut i = 0;
-    let mut last = chain[0];
-    for x in chain.iter().skip(1) {
-        if x - last == of {
-            i += 1;
-        }
-        last = *x;
+// Parses a comma-separated list of allowed gaps, like "1,2,3".
+fn parse_gaps(arg: Option<&String>) -> Vec<i64> {
+    match arg {
+        Some(s) => s.split(',').map(atoi).collect(),
+        None => vec![1, 2, 3],
     }
-    i
 }
 
-// how many valid chains include |from| and any nums greater than |from|?
-fn count(nums: &[i64], from: usize, memo: &mut HashMap<usize, i64>) -> i64 {
-    if let Some(n) = memo.get(&from) {
-        return *n;
-    } else if from >= nums.len() {
-        return 0;
-    }
-
-    let cur = nums[from];
-    let (a, b, c) = (from + 1, from + 2, from + 3);
-    let mut sum = count(nums, a, memo); // 1 X X
-    if b < nums.len() && nums[b] - cur <= 3 {
-        sum += count(nums, b, memo); // 0 1 X
-    }
-    if c < nums.len() && nums[c] - cur <= 3 {
-        sum += count(nums, c, memo); // 0 0 1
-    }
+fn read_adapters(path: &str, gaps: Option<&String>) -> Adapters {
+    let text = std::fs::read_to_string(path).unwrap();
+    let nums: Vec<_> = text.lines().map(atoi).collect();
+    Adapters::new(&nums, &parse_gaps(gaps)).unwrap()
+}
 
-    memo.insert(from, sum);
-    sum
+fn show(chain: &[i64]) -> String {
+    let chain: Vec<_> = chain.iter().map(|j| j.to_string()).collect();
+    chain.join(",")
 }
 
 fn main() {
-    let path = std::env::args().nth(1).unwrap();
-    let text = std::fs::read_to_string(&path).unwrap();
-    let mut nums: Vec<_> = text.lines().map(atoi).collect();
-    nums.push(0);
-    nums.sort();
-    nums.push(nums[nums.len() - 1] + 3);
-    println!("{}", differences(&nums, 1) * differences(&nums, 3));
-    let mut memo = HashMap::new();
-    memo.insert(nums.len() - 2, 1);
-    memo.insert(nums.len() - 1, 1);
-    println!("{}", count(&nums, 0, &mut memo));
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    match args[0].as_str() {
+        "stats" => {
+            let adapters = read_adapters(&args[1], args.get(2));
+            for (gap, n) in &adapters.histogram() {
+                println!("gap {}: {}", gap, n);
+            }
+            println!("uses every adapter: {}", adapters.uses_all());
+            println!("arrangements: {}", adapters.arrangements());
+            return;
+        }
+        "chains" => {
+            let adapters = read_adapters(&args[1], args.get(3));
+            let n = args[2].parse().unwrap();
+            for chain in adapters.chains().take(n) {
+                println!("{}", show(&chain));
+            }
+            return;
+        }
+        "sample" => {
+            // sample file n [gaps [seed]], seeded from the clock unless a
+            // seed is given; the seed goes to stderr to repeat the run
+            let adapters = read_adapters(&args[1], args.get(3));
+            let seed = match args.get(4) {
+                Some(seed) => seed.parse().unwrap(),
+                None => {
+                    let now = std::time::SystemTime::now();
+                    let t = now.duration_since(std::time::UNIX_EPOCH).unwrap();
+                    t.as_nanos() as u64
+                }
+            };
+            eprintln!("seed {}", seed);
+            let mut rng = Rng::new(seed);
+            for _ in 0..args[2].parse().unwrap() {
+                match adapters.sample(&mut rng) {
+                    Some(chain) => println!("{}", show(&chain)),
+                    None => println!("no valid chains"),
+                }
+            }
+            return;
+        }
+        "removable" => {
+            let adapters = read_adapters(&args[1], args.get(2));
+            match adapters.removable() {
+                Some(removed) => println!("{}", show(&removed)),
+                None => println!("no valid chains"),
+            }
+            return;
+        }
+        _ => {}
+    }
+
+    let adapters = read_adapters(&args[0], args.get(1));
+    let hist = adapters.histogram();
+    let diffs = |gap| hist.get(&gap).copied().unwrap_or(0);
+    println!("{}", diffs(1) * diffs(3));
+    println!("{}", adapters.arrangements());
 }
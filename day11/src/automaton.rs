@@ -0,0 +1,443 @@
+// automaton.rs: a generic 2D cellular automaton. Each generation, the next
+// state of every cell is looked up in a rule table from its current state
+// and how many of its neighbours are in the rule's counted state. Which
+// cells are neighbours is up to a pluggable Neighbourhood.
+//
+// A Simulation looks up each cell's neighbours once, keeps two buffers of
+// cell states that it swaps between generations, and only re-evaluates
+// cells that changed or had a neighbour change in the last generation.
+// It detects rules that never settle by hashing each generation's state.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+pub trait State: Copy + Eq + Hash {
+    fn from_char(c: char) -> Option<Self>;
+    fn to_char(self) -> char;
+}
+
+pub type Slope = (i32, i32);
+
+pub const MOORE: [Slope; 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+];
+
+pub const VON_NEUMANN: [Slope; 4] = [(-1, 0), (0, -1), (0, 1), (1, 0)];
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Grid<S> {
+    cells: Vec<S>,
+    rows: usize,
+    cols: usize,
+}
+
+impl<S: State> Grid<S> {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut cells = Vec::new();
+        let mut rows = 0;
+        let cols = s.lines().next().map_or(0, |line| line.chars().count());
+        for (row, line) in s.lines().enumerate() {
+            let err = |e| format!("line {}: {}", row + 1, e);
+            for c in line.chars() {
+                match S::from_char(c) {
+                    Some(cell) => cells.push(cell),
+                    None => return Err(err(format!("bad cell: {}", c))),
+                }
+            }
+            if cells.len() != (row + 1) * cols {
+                return Err(err(format!("expected {} cells", cols)));
+            }
+            rows += 1;
+        }
+        Ok(Grid { cells, rows, cols })
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    // The index of the cell at the given slope from cell i, if it's on the
+    // grid.
+    pub fn offset(&self, i: usize, (drow, dcol): Slope) -> Option<usize> {
+        let row = (i / self.cols) as i32 + drow;
+        let col = (i % self.cols) as i32 + dcol;
+        let on_grid = row >= 0
+            && row < self.rows as i32
+            && col >= 0
+            && col < self.cols as i32;
+        if on_grid {
+            Some(row as usize * self.cols + col as usize)
+        } else {
+            None
+        }
+    }
+
+    pub fn count(&self, state: S) -> usize {
+        self.cells.iter().filter(|&&s| s == state).count()
+    }
+}
+
+impl<S: State> fmt::Display for Grid<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.cols == 0 {
+            return Ok(());
+        }
+        for row in self.cells.chunks(self.cols) {
+            let line: String = row.iter().map(|s| s.to_char()).collect();
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+pub trait Neighbourhood<S> {
+    // The indices of the neighbours of cell i.
+    fn neighbours(&self, grid: &Grid<S>, i: usize) -> Vec<usize>;
+
+    // Whether the neighbours of a cell can change when some cell moves into
+    // or out of the given state. A Simulation rebuilds its neighbour lists
+    // when this is true, so only neighbourhoods that never look at cell
+    // states should return false for every state.
+    fn depends_on(&self, _state: S) -> bool {
+        true
+    }
+}
+
+// The cells at fixed offsets, such as MOORE or VON_NEUMANN.
+pub struct Offsets(pub Vec<Slope>);
+
+impl<S: State> Neighbourhood<S> for Offsets {
+    fn neighbours(&self, grid: &Grid<S>, i: usize) -> Vec<usize> {
+        self.0.iter().filter_map(|&d| grid.offset(i, d)).collect()
+    }
+
+    fn depends_on(&self, _state: S) -> bool {
+        false
+    }
+}
+
+// The first cell in each direction that isn't in the transparent state.
+pub struct LineOfSight<S> {
+    pub slopes: Vec<Slope>,
+    pub transparent: S,
+}
+
+impl<S: State> Neighbourhood<S> for LineOfSight<S> {
+    fn neighbours(&self, grid: &Grid<S>, i: usize) -> Vec<usize> {
+        let mut nbrs = Vec::new();
+        for &slope in &self.slopes {
+            let mut j = grid.offset(i, slope);
+            while let Some(k) = j {
+                if grid.cells[k] != self.transparent {
+                    break;
+                }
+                j = grid.offset(k, slope);
+            }
+            nbrs.extend(j);
+        }
+        nbrs
+    }
+
+    fn depends_on(&self, state: S) -> bool {
+        state == self.transparent
+    }
+}
+
+// Neighbour lists for every cell, stored end to end.
+struct Adjacency {
+    starts: Vec<usize>,
+    targets: Vec<usize>,
+}
+
+impl Adjacency {
+    fn new<I: Iterator<Item = Vec<usize>>>(lists: I) -> Self {
+        let mut adj = Adjacency {
+            starts: vec![0],
+            targets: Vec::new(),
+        };
+        for list in lists {
+            adj.targets.extend(list);
+            adj.starts.push(adj.targets.len());
+        }
+        adj
+    }
+
+    fn of(&self, i: usize) -> &[usize] {
+        &self.targets[self.starts[i]..self.starts[i + 1]]
+    }
+
+    // For each cell, the cells that have it as a neighbour.
+    fn reverse(&self) -> Adjacency {
+        let n = self.starts.len() - 1;
+        let mut lists = vec![Vec::new(); n];
+        for i in 0..n {
+            for &j in self.of(i) {
+                lists[j].push(i);
+            }
+        }
+        Adjacency::new(lists.into_iter())
+    }
+}
+
+pub struct Rule<S> {
+    counted: S,
+    table: HashMap<(S, usize), S>,
+}
+
+impl<S: State> Rule<S> {
+    // A rule that counts neighbours in the given state and, until entries
+    // are added, leaves every cell as it is.
+    pub fn new(counted: S) -> Self {
+        Rule {
+            counted,
+            table: HashMap::new(),
+        }
+    }
+
+    // Makes cells in state from become to when the number of counted
+    // neighbours is in counts.
+    pub fn with<I>(mut self, from: S, counts: I, to: S) -> Self
+    where
+        I: IntoIterator<Item = usize>,
+    {
+        for n in counts {
+            self.table.insert((from, n), to);
+        }
+        self
+    }
+
+    pub fn next(&self, state: S, counted: usize) -> S {
+        *self.table.get(&(state, counted)).unwrap_or(&state)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Life {
+    Dead,
+    Alive,
+}
+
+impl State for Life {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            '.' => Some(Life::Dead),
+            '#' => Some(Life::Alive),
+            _ => None,
+        }
+    }
+
+    fn to_char(self) -> char {
+        match self {
+            Life::Dead => '.',
+            Life::Alive => '#',
+        }
+    }
+}
+
+impl Rule<Life> {
+    // Parses a Life-like rule string such as "B3/S23", which gives the
+    // neighbour counts for which dead cells are born and live cells
+    // survive in a Moore neighbourhood.
+    pub fn life(spec: &str) -> Result<Self, String> {
+        let err = || format!("bad rule: {}", spec);
+        let (b, s) = spec.split_once('/').ok_or_else(err)?;
+        let counts = |part: &str, prefix: char| -> Result<Vec<usize>, String> {
+            let digits = part
+                .strip_prefix(prefix)
+                .or_else(|| part.strip_prefix(prefix.to_ascii_lowercase()))
+                .ok_or_else(err)?;
+            digits
+                .chars()
+                .map(|c| match c.to_digit(10) {
+                    Some(n) if n as usize <= MOORE.len() => Ok(n as usize),
+                    _ => Err(err()),
+                })
+                .collect()
+        };
+        let (born, survive) = (counts(b, 'B')?, counts(s, 'S')?);
+        let die = (0..=MOORE.len()).filter(|n| !survive.contains(n));
+        Ok(Rule::new(Life::Alive)
+            .with(Life::Dead, born, Life::Alive)
+            .with(Life::Alive, die, Life::Dead))
+    }
+}
+
+pub struct Automaton<S, N> {
+    rule: Rule<S>,
+    nbhd: N,
+}
+
+impl<S: State, N: Neighbourhood<S>> Automaton<S, N> {
+    pub fn new(rule: Rule<S>, nbhd: N) -> Self {
+        Automaton { rule, nbhd }
+    }
+
+    pub fn step(&self, grid: &Grid<S>) -> Grid<S> {
+        let cells = (0..grid.len())
+            .map(|i| {
+                let counted = self
+                    .nbhd
+                    .neighbours(grid, i)
+                    .into_iter()
+                    .filter(|&j| grid.cells[j] == self.rule.counted)
+                    .count();
+                self.rule.next(grid.cells[i], counted)
+            })
+            .collect();
+        Grid {
+            cells,
+            rows: grid.rows,
+            cols: grid.cols,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    // the grid stops changing at this generation
+    Fixed(usize),
+    // the grid at start + period is the same as at start
+    Cycle { start: usize, period: usize },
+    BudgetExceeded,
+}
+
+pub struct Simulation<'a, S, N> {
+    automaton: &'a Automaton<S, N>,
+    cur: Grid<S>,
+    // matches cur except while a step is being computed
+    next: Vec<S>,
+    nbrs: Adjacency,
+    rnbrs: Adjacency,
+    // the cells to re-evaluate in the next step, without repeats
+    dirty: Vec<usize>,
+    is_dirty: Vec<bool>,
+    generation: usize,
+}
+
+impl<'a, S: State, N: Neighbourhood<S>> Simulation<'a, S, N> {
+    pub fn new(automaton: &'a Automaton<S, N>, grid: Grid<S>) -> Self {
+        let next = grid.cells.clone();
+        let (nbrs, rnbrs) = Self::adjacency(automaton, &grid);
+        Simulation {
+            automaton,
+            dirty: (0..grid.len()).collect(),
+            is_dirty: vec![true; grid.len()],
+            cur: grid,
+            next,
+            nbrs,
+            rnbrs,
+            generation: 0,
+        }
+    }
+
+    fn adjacency(
+        automaton: &Automaton<S, N>,
+        grid: &Grid<S>,
+    ) -> (Adjacency, Adjacency) {
+        let nbhd = &automaton.nbhd;
+        let nbrs =
+            Adjacency::new((0..grid.len()).map(|i| nbhd.neighbours(grid, i)));
+        let rnbrs = nbrs.reverse();
+        (nbrs, rnbrs)
+    }
+
+    pub fn grid(&self) -> &Grid<S> {
+        &self.cur
+    }
+
+    // Advances one generation and returns how many cells changed.
+    pub fn step(&mut self) -> usize {
+        self.generation += 1;
+        let rule = &self.automaton.rule;
+        let cells = &self.cur.cells;
+        for &i in &self.dirty {
+            let counted = self
+                .nbrs
+                .of(i)
+                .iter()
+                .filter(|&&j| cells[j] == rule.counted)
+                .count();
+            self.next[i] = rule.next(cells[i], counted);
+        }
+        std::mem::swap(&mut self.cur.cells, &mut self.next);
+
+        let mut changed = Vec::new();
+        for i in self.dirty.drain(..) {
+            self.is_dirty[i] = false;
+            if self.cur.cells[i] != self.next[i] {
+                changed.push(i);
+            }
+        }
+        // if a change can move some cell's neighbours, like a seat turning
+        // into floor under LineOfSight, start over with fresh lists
+        let nbhd = &self.automaton.nbhd;
+        let moved = changed.iter().any(|&i| {
+            nbhd.depends_on(self.cur.cells[i]) || nbhd.depends_on(self.next[i])
+        });
+        for &i in &changed {
+            self.next[i] = self.cur.cells[i];
+        }
+        if moved {
+            let (nbrs, rnbrs) = Self::adjacency(self.automaton, &self.cur);
+            self.nbrs = nbrs;
+            self.rnbrs = rnbrs;
+            self.dirty.extend(0..self.cur.len());
+            self.is_dirty.iter_mut().for_each(|d| *d = true);
+            return changed.len();
+        }
+        for &i in &changed {
+            for &j in std::iter::once(&i).chain(self.rnbrs.of(i)) {
+                if !self.is_dirty[j] {
+                    self.is_dirty[j] = true;
+                    self.dirty.push(j);
+                }
+            }
+        }
+        changed.len()
+    }
+
+    fn hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.cur.cells.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    // Steps until the grid stops changing, returns to an earlier state, or
+    // budget generations have passed. A repeated hash is checked by running
+    // one more period and comparing the grids, so a reported period is
+    // exact, though a hash collision could make its start too early.
+    pub fn run(&mut self, budget: usize) -> Outcome {
+        let end = self.generation + budget;
+        let mut seen = HashMap::new();
+        loop {
+            let hash = self.hash();
+            if let Some(&start) = seen.get(&hash) {
+                let period = self.generation - start;
+                let cells = self.cur.cells.clone();
+                for _ in 0..period {
+                    self.step();
+                }
+                if self.cur.cells == cells {
+                    return Outcome::Cycle { start, period };
+                }
+                continue;
+            }
+            seen.insert(hash, self.generation);
+            if self.generation >= end {
+                return Outcome::BudgetExceeded;
+            }
+            if self.step() == 0 {
+                return Outcome::Fixed(self.generation - 1);
+            }
+        }
+    }
+}
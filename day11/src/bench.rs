@@ -0,0 +1,96 @@
+// bench.rs: compares stepping the automaton naively, which finds every
+// cell's neighbours and allocates a new grid each generation, against a
+// Simulation on large generated seat layouts. Run with
+// `cargo run --release bench`.
+
+use crate::automaton::{
+    Automaton, Grid, LineOfSight, Neighbourhood, Offsets, Simulation, MOORE,
+};
+use crate::{seat_rule, Seat};
+use std::time::{Duration, Instant};
+
+// About one cell in eight is floor; the same seed gives the same layout.
+fn layout(rows: usize, cols: usize, seed: u64) -> Grid<Seat> {
+    let mut state = seed;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    let mut s = String::new();
+    for _ in 0..rows {
+        s.extend((0..cols).map(|_| if next() % 8 == 0 { '.' } else { 'L' }));
+        s.push('\n');
+    }
+    Grid::parse(&s).unwrap()
+}
+
+fn time<T>(f: impl Fn() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let result = f();
+    (result, start.elapsed())
+}
+
+// Random layouts tend to end up oscillating instead of settling, so both
+// run for a fixed number of generations.
+const GENERATIONS: usize = 100;
+
+fn naive<N>(a: &Automaton<Seat, N>, g: &Grid<Seat>) -> Grid<Seat>
+where
+    N: Neighbourhood<Seat>,
+{
+    let mut g = g.clone();
+    for _ in 0..GENERATIONS {
+        g = a.step(&g);
+    }
+    g
+}
+
+fn simulated<N>(a: &Automaton<Seat, N>, g: &Grid<Seat>) -> Grid<Seat>
+where
+    N: Neighbourhood<Seat>,
+{
+    let mut sim = Simulation::new(a, g.clone());
+    for _ in 0..GENERATIONS {
+        sim.step();
+    }
+    sim.grid().clone()
+}
+
+fn compare<N: Neighbourhood<Seat>>(
+    name: &str,
+    a: &Automaton<Seat, N>,
+    g: &Grid<Seat>,
+) -> String {
+    let (expected, naive_time) = time(|| naive(a, g));
+    let (actual, sim_time) = time(|| simulated(a, g));
+    assert_eq!(expected, actual);
+    format!(
+        "{} naive {:?}, simulation {:?} ({:.1}x)",
+        name,
+        naive_time,
+        sim_time,
+        naive_time.as_secs_f64() / sim_time.as_secs_f64()
+    )
+}
+
+pub fn run() {
+    for &(rows, cols) in &[(100, 100), (200, 200), (400, 400)] {
+        let grid = layout(rows, cols, 0x2020_1211);
+        let adjacent =
+            Automaton::new(seat_rule(4, MOORE.len()), Offsets(MOORE.to_vec()));
+        let sight = LineOfSight {
+            slopes: MOORE.to_vec(),
+            transparent: Seat::Floor,
+        };
+        let sight = Automaton::new(seat_rule(5, MOORE.len()), sight);
+        println!(
+            "{}x{}: {}; {}",
+            rows,
+            cols,
+            compare("adjacent", &adjacent, &grid),
+            compare("sight", &sight, &grid)
+        );
+    }
+}
@@ -1,142 +1,142 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-struct Pt2 {
-    row: i32,
-    col: i32,
-}
-
-type Slope = (i32, i32);
-
-impl Pt2 {
-    fn new(row: i32, col: i32) -> Self {
-        Pt2 { row, col }
-    }
-
-    fn add(&self, slope: Slope) -> Self {
-        let (drow, dcol) = slope;
-        Pt2::new(self.row + drow, self.col + dcol)
-    }
-}
-
-const SLOPES: [Slope; 8] = [
-    (-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, 1), (1, 0), (1, -1),
+mod automaton;
+mod bench;
+
+use automaton::{
+    Automaton, Grid, Life, LineOfSight, Offsets, Outcome, Rule, Simulation,
+    Slope, State, MOORE, VON_NEUMANN,
+};
+
+// A custom neighbourhood: the cells a knight's move away.
+const KNIGHT: [Slope; 8] = [
+    (-2, -1),
+    (-2, 1),
+    (-1, -2),
+    (-1, 2),
+    (1, -2),
+    (1, 2),
+    (2, -1),
+    (2, 1),
 ];
 
-struct Grid {
-    m: Vec<char>,
-    rows: usize,
-    cols: usize,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Seat {
+    Floor,
+    Empty,
+    Occupied,
 }
 
-impl Grid {
-    fn parse(s: &str) -> Self {
-        let rows: usize = s.lines().count();
-        let cols: usize = s.lines().next().unwrap().len();
-        let m = s.lines().flat_map(|line| line.chars()).collect();
-        Grid { m, rows, cols }
-    }
-
-    fn pt(&self, i: usize) -> Pt2 {
-        Pt2::new((i / self.cols) as i32, (i % self.cols) as i32)
+impl State for Seat {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            '.' => Some(Seat::Floor),
+            'L' => Some(Seat::Empty),
+            '#' => Some(Seat::Occupied),
+            _ => None,
+        }
     }
 
-    fn step<F>(&self, f: F) -> Self
-    where F: Fn(&Pt2, char) -> char {
-        let m = self
-            .m
-            .iter()
-            .enumerate()
-            .map(|(i, tile)| f(&self.pt(i), *tile))
-            .collect();
-        let (rows, cols) = (self.rows, self.cols);
-        Self { m, rows, cols }
+    fn to_char(self) -> char {
+        match self {
+            Seat::Floor => '.',
+            Seat::Empty => 'L',
+            Seat::Occupied => '#',
+        }
     }
+}
 
-    fn in_bounds(&self, pt: &Pt2) -> bool {
-        pt.row >= 0
-            && pt.row < self.rows as i32
-            && pt.col >= 0
-            && pt.col < self.cols as i32
-    }
+// Empty seats with no occupied neighbours fill up, and occupied seats with
+// at least threshold occupied neighbours empty out.
+fn seat_rule(threshold: usize, max: usize) -> Rule<Seat> {
+    Rule::new(Seat::Occupied)
+        .with(Seat::Empty, 0..=0, Seat::Occupied)
+        .with(Seat::Occupied, threshold..=max, Seat::Empty)
+}
 
-    fn get(&self, pt: &Pt2) -> char {
-        let i = pt.row * self.cols as i32 + pt.col;
-        self.m[i as usize]
-    }
+const BUDGET: usize = 10_000;
+
+// Runs the seat rule until it settles, returning how it settled and the
+// number of occupied seats at that point.
+fn occupied<N: automaton::Neighbourhood<Seat>>(
+    grid: &Grid<Seat>,
+    nbhd: N,
+    threshold: usize,
+    max: usize,
+) -> (Outcome, usize) {
+    let automaton = Automaton::new(seat_rule(threshold, max), nbhd);
+    let mut sim = Simulation::new(&automaton, grid.clone());
+    let outcome = sim.run(BUDGET);
+    (outcome, sim.grid().count(Seat::Occupied))
+}
 
-    fn step1(&self) -> Self {
-        self.step(|pt, tile| {
-            let occupied = SLOPES
-                .iter()
-                .map(|slope| pt.add(*slope))
-                .filter(|q| self.in_bounds(q))
-                .filter(|q| self.get(q) == '#')
-                .count();
-            let tile2 = match (tile, occupied) {
-                ('L', 0) => '#',
-                ('#', n) if n >= 4 => 'L',
-                _ => tile,
-            };
-            tile2
-        })
+fn stable(result: (Outcome, usize)) -> usize {
+    match result {
+        (Outcome::Fixed(_), n) => n,
+        (outcome, _) => panic!("seats never settle: {:?}", outcome),
     }
+}
 
-    fn nbr_in_dir(&self, pt: &Pt2, slope: &(i32, i32)) -> Option<char> {
-        let (drow, dcol) = slope;
-        let apply = |pt: &Pt2| Pt2::new(pt.row + drow, pt.col + dcol);
-        let mut pt2 = apply(&pt);
-        while self.in_bounds(&pt2) && self.get(&pt2) == '.' {
-            pt2 = apply(&pt2);
+fn main() {
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    match args[0].as_str() {
+        "bench" => {
+            bench::run();
+            return;
         }
-        if !self.in_bounds(&pt2) {
-            None
-        } else {
-            Some(self.get(&pt2))
+        "life" => {
+            let rule = Rule::life(&args[1]).unwrap();
+            let text = std::fs::read_to_string(&args[2]).unwrap();
+            let grid: Grid<Life> = Grid::parse(&text).unwrap();
+            let automaton = Automaton::new(rule, Offsets(MOORE.to_vec()));
+            let mut sim = Simulation::new(&automaton, grid);
+            for _ in 0..args[3].parse().unwrap() {
+                sim.step();
+            }
+            print!("{}", sim.grid());
+            return;
         }
-    }
-
-    fn step2(&self) -> Self {
-        self.step(|pt, tile| {
-            let occupied = SLOPES
-                .iter()
-                .filter_map(|slope| self.nbr_in_dir(pt, slope))
-                .filter(|t| *t == '#')
-                .count();
-            let tile2 = match (tile, occupied) {
-                ('L', 0) => '#',
-                ('#', n) if n >= 5 => 'L',
-                _ => tile,
+        "seats" => {
+            let text = std::fs::read_to_string(&args[1]).unwrap();
+            let grid = Grid::parse(&text).unwrap();
+            let threshold = args[3].parse().unwrap();
+            let (outcome, n) = match args[2].as_str() {
+                "moore" => {
+                    let nbhd = Offsets(MOORE.to_vec());
+                    occupied(&grid, nbhd, threshold, MOORE.len())
+                }
+                "vonneumann" => {
+                    let nbhd = Offsets(VON_NEUMANN.to_vec());
+                    occupied(&grid, nbhd, threshold, VON_NEUMANN.len())
+                }
+                "knight" => {
+                    let nbhd = Offsets(KNIGHT.to_vec());
+                    occupied(&grid, nbhd, threshold, KNIGHT.len())
+                }
+                "sight" => {
+                    let nbhd = LineOfSight {
+                        slopes: MOORE.to_vec(),
+                        transparent: Seat::Floor,
+                    };
+                    occupied(&grid, nbhd, threshold, MOORE.len())
+                }
+                nbhd => panic!("bad neighbourhood: {}", nbhd),
             };
-            tile2
-        })
-    }
-
-    fn step_until_stable<F>(&self, f: F) -> Self
-    where F: Fn(&Self) -> Self {
-        let mut last = f(self);
-        loop {
-            let new = f(&last);
-            if last.m.iter().enumerate().any(|(i, v)| new.m[i] != *v) {
-                last = new;
-                continue;
-            }
-            break;
+            println!("{:?}: {} occupied", outcome, n);
+            return;
         }
-        last
+        _ => {}
     }
-}
 
-fn occupied(g: &Grid) -> usize {
-    g.m.iter().filter(|v| **v == '#').count()
-}
-
-fn main() {
-    let path = std::env::args().nth(1).unwrap();
-    let text = std::fs::read_to_string(&path).unwrap();
-    let grid = Grid::parse(&text);
+    let text = std::fs::read_to_string(&args[0]).unwrap();
+    let grid = Grid::parse(&text).unwrap();
 
-    let stable1 = grid.step_until_stable(|g| g.step1());
-    println!("{}", occupied(&stable1));
+    println!(
+        "{}",
+        stable(occupied(&grid, Offsets(MOORE.to_vec()), 4, MOORE.len()))
+    );
 
-    let stable2 = grid.step_until_stable(|g| g.step2());
-    println!("{}", occupied(&stable2));
+    let sight = LineOfSight {
+        slopes: MOORE.to_vec(),
+        transparent: Seat::Floor,
+    };
+    println!("{}", stable(occupied(&grid, sight, 5, MOORE.len())));
 }
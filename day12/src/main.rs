@@ -1,56 +1,63 @@
-struct Instr {
-    cmd: char,
-    amt: i32,
-}
+mod planner;
+mod trajectory;
+
+use std::fmt;
 
-fn parse(s: &str) -> Instr {
-    let cmd = s.chars().next().unwrap();
-    let amt = i32::from_str_radix(&s[1..], 10).unwrap();
-    Instr { cmd, amt }
+// Headings and turns are in degrees clockwise from north, so R90 is a turn
+// of 90 and L90 a turn of -90.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Instr {
+    Move(i32, i32),
+    Turn(i32),
+    Forward(i32),
 }
 
-enum Dir { North, East, South, West }
+const HEADINGS: [(&str, i32); 8] = [
+    ("N", 0),
+    ("NE", 45),
+    ("E", 90),
+    ("SE", 135),
+    ("S", 180),
+    ("SW", 225),
+    ("W", 270),
+    ("NW", 315),
+];
 
-impl Dir {
-    fn parse(ch: char) -> Self {
-        match ch {
-            'N' => Self::North,
-            'S' => Self::South,
-            'E' => Self::East,
-            'W' => Self::West,
-            _ => unreachable!(),
-        }
-    }
-
-    fn rotate(&self, dir: char) -> Self {
-        match (dir, self) {
-            ('R', Self::North) => Self::East,
-            ('R', Self::East )=> Self::South,
-            ('R', Self::South) => Self::West,
-            ('R', Self::West )=> Self::North,
-            ('L', Self::North) => Self::West,
-            ('L', Self::East )=> Self::North,
-            ('L', Self::South) => Self::East,
-            ('L', Self::West )=> Self::South,
-            _ => unreachable!(),
-        }
+fn parse(s: &str) -> Result<Instr, String> {
+    let split = s.find(|c: char| !c.is_ascii_alphabetic());
+    let (cmd, amt) = s.split_at(split.unwrap_or(s.len()));
+    let amt: i32 = amt.parse().map_err(|_| format!("bad amount: {:?}", amt))?;
+    match cmd {
+        "L" => amt.checked_neg().map(Instr::Turn).ok_or("bad turn".into()),
+        "R" => Ok(Instr::Turn(amt)),
+        "F" => Ok(Instr::Forward(amt)),
+        _ => match HEADINGS.iter().find(|&&(name, _)| name == cmd) {
+            Some(&(_, heading)) => Ok(Instr::Move(heading, amt)),
+            None => Err(format!("bad command: {:?}", cmd)),
+        },
     }
+}
 
-    fn slope(&self) -> Pt2 {
-        match self {
-            Self::North => Pt2(0, 1),
-            Self::East => Pt2(1, 0),
-            Self::South => Pt2(0, -1),
-            Self::West => Pt2(-1, 0),
+impl fmt::Display for Instr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Instr::Move(heading, amt) => {
+                let name = HEADINGS.iter().find(|h| h.1 == heading).unwrap().0;
+                write!(f, "{}{}", name, amt)
+            }
+            Instr::Turn(degrees) if degrees < 0 => write!(f, "L{}", -degrees),
+            Instr::Turn(degrees) => write!(f, "R{}", degrees),
+            Instr::Forward(amt) => write!(f, "F{}", amt),
         }
     }
 }
 
-#[derive(Clone, Copy)]
-struct Pt2(i32, i32);
+// x is east and y is north.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Pt2(f64, f64);
 
 impl Pt2 {
-    fn scale(&self, amt: i32) -> Self {
+    fn scale(&self, amt: f64) -> Self {
         Self(self.0 * amt, self.1 * amt)
     }
 
@@ -58,133 +65,259 @@ impl Pt2 {
         Self(self.0 + pt.0, self.1 + pt.1)
     }
 
-    fn sub(&self, pt: &Self) -> Self {
-        Self(self.0 - pt.0, self.1 - pt.1)
+    fn norm(&self) -> f64 {
+        self.0.abs() + self.1.abs()
     }
+}
 
-    fn norm(&self) -> i32 {
-        self.0.abs() + self.1.abs()
+// Exact math only allows right angles, so that every coordinate stays an
+// integer. Float math allows any angle, and snaps coordinates that are
+// within the tolerance of an integer to it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Math {
+    Exact,
+    Float { tolerance: f64 },
+}
+
+impl Math {
+    // The unit vector for a heading.
+    fn unit(self, heading: i32) -> Result<Pt2, String> {
+        match (heading.rem_euclid(360), self) {
+            (0, _) => Ok(Pt2(0.0, 1.0)),
+            (90, _) => Ok(Pt2(1.0, 0.0)),
+            (180, _) => Ok(Pt2(0.0, -1.0)),
+            (270, _) => Ok(Pt2(-1.0, 0.0)),
+            (_, Math::Exact) => Err(format!(
+                "{} degrees needs float math: pass float <tolerance> first",
+                heading
+            )),
+            (deg, Math::Float { .. }) => {
+                let rad = (deg as f64).to_radians();
+                Ok(Pt2(rad.sin(), rad.cos()))
+            }
+        }
+    }
+
+    fn rotate(self, v: Pt2, degrees: i32) -> Result<Pt2, String> {
+        let Pt2(sin, cos) = self.unit(degrees)?;
+        Ok(self.snap(Pt2(v.0 * cos + v.1 * sin, v.1 * cos - v.0 * sin)))
     }
 
-    fn rotate(&self) -> Self {
-        let (x, y) = (self.1.abs(), self.0.abs());
-        let (xs, ys) = match (self.0.signum(), self.1.signum()) {
-            (-1, -1) => (-1, 1),
-            (-1,  0) => (0, 1),
-            (-1,  1) => (1, 1),
-            ( 0, -1) => (-1, 0),
-            ( 0,  1) => (1, 0),
-            ( 1, -1) => (-1, -1),
-            ( 1,  0) => (0, -1),
-            ( 1,  1) => (1, -1),
-            _ => unreachable!(),
+    fn snap(self, pt: Pt2) -> Pt2 {
+        let snap = |x: f64| match self {
+            Math::Float { tolerance } if (x - x.round()).abs() <= tolerance => {
+                x.round()
+            }
+            _ => x,
         };
-        Self(x * xs, y * ys)
-    }
-
-    fn rotate_by(&self, dir: char, amt: i32) -> Self {
-        assert!(amt % 90 == 0);
-        let n = amt / 90;
-        let mut pt = *self;
-        for _ in 0..n {
-            match dir {
-                'R' => pt = pt.rotate(),
-                'L' => pt = pt.rotate().rotate().rotate(),
-                _ => unreachable!(),
+        Pt2(snap(pt.0), snap(pt.1))
+    }
+}
+
+trait Navigator {
+    fn math(&self) -> Math;
+    fn loc(&self) -> Pt2;
+    // The direction F moves in, in degrees clockwise from north.
+    fn heading(&self) -> f64;
+    fn waypoint(&self) -> Option<Pt2> {
+        None
+    }
+    // Moves by v for N, S, E, W and the diagonals.
+    fn shift(&mut self, v: Pt2);
+    fn turn(&mut self, degrees: i32) -> Result<(), String>;
+    fn forward(&mut self, amt: i32);
+
+    fn exec(&mut self, instr: &Instr) -> Result<(), String> {
+        match *instr {
+            Instr::Move(heading, amt) => {
+                let v = self.math().unit(heading)?.scale(amt as f64);
+                self.shift(v);
             }
+            Instr::Turn(degrees) => self.turn(degrees)?,
+            Instr::Forward(amt) => self.forward(amt),
+        }
+        Ok(())
+    }
+
+    fn run(&mut self, instrs: &[Instr]) -> Result<(), String> {
+        for (i, instr) in instrs.iter().enumerate() {
+            let err = |e| format!("instruction {}: {}", i + 1, e);
+            self.exec(instr).map_err(err)?;
         }
-        pt
+        Ok(())
     }
 }
 
+#[derive(Clone)]
 struct WaypointShip {
+    math: Math,
+    // relative to loc
     waypt: Pt2,
     loc: Pt2,
 }
 
 impl WaypointShip {
-    fn new() -> Self {
-        Self { waypt: Pt2(10, 1), loc: Pt2(0, 0) }
+    fn new(math: Math) -> Self {
+        Self {
+            math,
+            waypt: Pt2(10.0, 1.0),
+            loc: Pt2(0.0, 0.0),
+        }
     }
+}
 
-    fn rotate(&mut self, dir: char, amt: i32) {
-        let vec = self.waypt.sub(&self.loc);
-        let vec = vec.rotate_by(dir, amt);
-        self.waypt = vec.add(&self.loc);
+impl Navigator for WaypointShip {
+    fn math(&self) -> Math {
+        self.math
     }
 
-    fn advance(&mut self, amt: i32) {
-        let vec = self.waypt.sub(&self.loc).scale(amt);
-        self.loc = self.loc.add(&vec);
-        self.waypt = self.waypt.add(&vec);
+    fn loc(&self) -> Pt2 {
+        self.loc
     }
 
-    fn shift(&mut self, dir: char, amt: i32) {
-        let dir = Dir::parse(dir).slope();
-        let vec = dir.scale(amt);
-        self.waypt = self.waypt.add(&vec);
+    fn heading(&self) -> f64 {
+        self.waypt
+            .0
+            .atan2(self.waypt.1)
+            .to_degrees()
+            .rem_euclid(360.0)
     }
 
-    fn exec(&mut self, i: &Instr) {
-        match i.cmd {
-            'N' | 'E' | 'S' | 'W' => self.shift(i.cmd, i.amt),
-            'R' | 'L' => self.rotate(i.cmd, i.amt),
-            'F' => self.advance(i.amt),
-            _ => unreachable!(),
-        }
+    fn waypoint(&self) -> Option<Pt2> {
+        Some(self.loc.add(&self.waypt))
+    }
+
+    fn shift(&mut self, v: Pt2) {
+        self.waypt = self.math.snap(self.waypt.add(&v));
+    }
+
+    fn turn(&mut self, degrees: i32) -> Result<(), String> {
+        self.waypt = self.math.rotate(self.waypt, degrees)?;
+        Ok(())
+    }
+
+    fn forward(&mut self, amt: i32) {
+        let v = self.waypt.scale(amt as f64);
+        self.loc = self.math.snap(self.loc.add(&v));
     }
 }
 
+#[derive(Clone)]
 struct Ship {
-    dir: Dir,
+    math: Math,
+    heading: i32,
+    dir: Pt2,
     loc: Pt2,
 }
 
 impl Ship {
-    fn new() -> Self {
-        Self { dir: Dir::East, loc: Pt2(0, 0) }
+    fn new(math: Math) -> Self {
+        Self {
+            math,
+            heading: 90,
+            dir: Pt2(1.0, 0.0),
+            loc: Pt2(0.0, 0.0),
+        }
     }
+}
 
-    fn rotate(&mut self, dir: char, amt: i32) {
-        assert!(amt % 90 == 0);
-        let n = amt / 90;
-        for _ in 0..n {
-            self.dir = self.dir.rotate(dir);
-        }
+impl Navigator for Ship {
+    fn math(&self) -> Math {
+        self.math
     }
 
-    fn advance(&mut self, amt: i32) {
-        let dir = self.dir.slope();
-        let vec = dir.scale(amt);
-        self.loc = self.loc.add(&vec);
+    fn loc(&self) -> Pt2 {
+        self.loc
     }
 
-    fn shift(&mut self, dir: char, amt: i32) {
-        let dir = Dir::parse(dir).slope();
-        let vec = dir.scale(amt);
-        self.loc = self.loc.add(&vec);
+    fn heading(&self) -> f64 {
+        self.heading as f64
     }
 
-    fn exec(&mut self, i: &Instr) {
-        match i.cmd {
-            'N' | 'E' | 'S' | 'W' => self.shift(i.cmd, i.amt),
-            'R' | 'L' => self.rotate(i.cmd, i.amt),
-            'F' => self.advance(i.amt),
-            _ => unreachable!(),
-        }
+    fn shift(&mut self, v: Pt2) {
+        self.loc = self.math.snap(self.loc.add(&v));
+    }
+
+    fn turn(&mut self, degrees: i32) -> Result<(), String> {
+        self.math.unit(degrees)?;
+        let heading = (self.heading + degrees % 360).rem_euclid(360);
+        self.dir = self.math.unit(heading)?;
+        self.heading = heading;
+        Ok(())
     }
+
+    fn forward(&mut self, amt: i32) {
+        let v = self.dir.scale(amt as f64);
+        self.loc = self.math.snap(self.loc.add(&v));
+    }
+}
+
+fn read_instrs(path: &str) -> Vec<Instr> {
+    let text = std::fs::read_to_string(path).unwrap();
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| {
+            parse(line).map_err(|e| format!("line {}: {}", i + 1, e))
+        })
+        .collect::<Result<_, _>>()
+        .unwrap()
 }
 
+// usage: [float <tolerance>] <path>,
+//        [float <tolerance>] trace <ship|waypoint> <svg|geojson> <path>, or
+//        plan <ship|waypoint> <instructions|movement> <x> <y>
+// Diagonal headings and turns that aren't a multiple of 90 only work with
+// float math, since moves along them don't land on integer coordinates.
 fn main() {
-    let path = std::env::args().nth(1).unwrap();
-    let text = std::fs::read_to_string(&path).unwrap();
-    let instrs: Vec<_> = text.lines().map(parse).collect();
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    let (math, args) = match args[0].as_str() {
+        "float" => {
+            let tolerance = args[1].parse().unwrap();
+            (Math::Float { tolerance }, &args[2..])
+        }
+        _ => (Math::Exact, &args[..]),
+    };
+    if args[0] == "plan" {
+        let cost = match args[2].as_str() {
+            "instructions" => planner::Cost::Instructions,
+            "movement" => planner::Cost::Movement,
+            cost => panic!("bad cost model: {}", cost),
+        };
+        let target = Pt2(args[3].parse().unwrap(), args[4].parse().unwrap());
+        let plan = match args[1].as_str() {
+            "ship" => planner::plan(&Ship::new(math), target, cost),
+            "waypoint" => planner::plan(&WaypointShip::new(math), target, cost),
+            nav => panic!("bad ship: {}", nav),
+        };
+        for instr in plan.unwrap() {
+            println!("{}", instr);
+        }
+        return;
+    }
+    if args[0] == "trace" {
+        let instrs = read_instrs(&args[3]);
+        let trajectory = match args[1].as_str() {
+            "ship" => trajectory::record(&mut Ship::new(math), &instrs),
+            "waypoint" => {
+                trajectory::record(&mut WaypointShip::new(math), &instrs)
+            }
+            nav => panic!("bad ship: {}", nav),
+        };
+        let trajectory = trajectory.unwrap();
+        match args[2].as_str() {
+            "svg" => print!("{}", trajectory.svg()),
+            "geojson" => print!("{}", trajectory.geojson()),
+            format => panic!("bad format: {}", format),
+        }
+        return;
+    }
+    let instrs = read_instrs(&args[0]);
 
-    let mut ship = Ship::new();
-    instrs.iter().for_each(|i| ship.exec(i));
-    println!("{}", ship.loc.norm());
+    let mut ship = Ship::new(math);
+    ship.run(&instrs).unwrap();
+    println!("{}", ship.loc().norm());
 
-    let mut ship2 = WaypointShip::new();
-    instrs.iter().for_each(|i| ship2.exec(i));
-    println!("{}", ship2.loc.norm());
+    let mut ship2 = WaypointShip::new(math);
+    ship2.run(&instrs).unwrap();
+    println!("{}", ship2.loc().norm());
 }
@@ -0,0 +1,168 @@
+// planner.rs: finds the cheapest instructions that take a ship from where
+// it is to a target, using Dijkstra's algorithm over ship states. Rather
+// than trying every amount, each state only offers the moves that could be
+// part of a cheapest plan: moves that line the ship (or, for a waypoint
+// ship, the waypoint) up with the target, turns, and forward moves that
+// land exactly on the target's row, column or position. Plans are in exact
+// math, and are replayed through exec before they're returned.
+
+use crate::{Instr, Math, Navigator, Pt2, Ship, WaypointShip};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cost {
+    // each instruction costs one
+    Instructions,
+    // the Manhattan distance the ship travels, then the instructions
+    Movement,
+}
+
+pub trait Plannable: Navigator + Clone {
+    // Identifies the state of the ship.
+    fn key(&self) -> [i64; 4];
+    // The instructions worth trying next on the way to target.
+    fn candidates(&self, target: Pt2) -> Vec<Instr>;
+}
+
+const TURNS: [Instr; 3] = [Instr::Turn(90), Instr::Turn(180), Instr::Turn(-90)];
+
+// The instruction that moves by amt along an axis, if amt isn't zero.
+fn shift(amt: i64, pos: i32, neg: i32) -> Option<Instr> {
+    match amt {
+        0 => None,
+        n if n > 0 => Some(Instr::Move(pos, n as i32)),
+        n => Some(Instr::Move(neg, -n as i32)),
+    }
+}
+
+fn remaining(nav: &impl Navigator, target: Pt2) -> (i64, i64) {
+    let loc = nav.loc();
+    ((target.0 - loc.0) as i64, (target.1 - loc.1) as i64)
+}
+
+impl Plannable for Ship {
+    fn key(&self) -> [i64; 4] {
+        [self.loc.0 as i64, self.loc.1 as i64, self.heading as i64, 0]
+    }
+
+    fn candidates(&self, target: Pt2) -> Vec<Instr> {
+        let (rx, ry) = remaining(self, target);
+        let mut instrs: Vec<_> = shift(rx, 90, 270)
+            .into_iter()
+            .chain(shift(ry, 0, 180))
+            .chain(TURNS.iter().copied())
+            .collect();
+        let (dx, dy) = (self.dir.0 as i64, self.dir.1 as i64);
+        for &(r, d) in &[(rx, dx), (ry, dy)] {
+            if r * d > 0 {
+                instrs.push(Instr::Forward(r.abs() as i32));
+            }
+        }
+        instrs
+    }
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+impl Plannable for WaypointShip {
+    fn key(&self) -> [i64; 4] {
+        let (loc, w) = (self.loc, self.waypt);
+        [loc.0 as i64, loc.1 as i64, w.0 as i64, w.1 as i64]
+    }
+
+    // The last forward move has to go straight to the target, so the
+    // waypoint has to end up at the remaining distance divided by some k.
+    fn candidates(&self, target: Pt2) -> Vec<Instr> {
+        let (rx, ry) = remaining(self, target);
+        let (wx, wy) = (self.waypt.0 as i64, self.waypt.1 as i64);
+        let mut instrs = TURNS.to_vec();
+        let g = gcd(rx.abs(), ry.abs());
+        for k in (1..).take_while(|k| k * k <= g).filter(|k| g % k == 0) {
+            for &k in &[k, g / k] {
+                let (tx, ty) = (rx / k, ry / k);
+                if (tx, ty) == (wx, wy) {
+                    instrs.push(Instr::Forward(k as i32));
+                }
+                instrs.extend(shift(tx - wx, 90, 270));
+                instrs.extend(shift(ty - wy, 0, 180));
+            }
+        }
+        instrs
+    }
+}
+
+fn cost(cost: Cost, from: Pt2, to: Pt2) -> (u64, u64) {
+    match cost {
+        Cost::Instructions => (1, 0),
+        Cost::Movement => {
+            let moved = (to.0 - from.0).abs() + (to.1 - from.1).abs();
+            (moved as u64, 1)
+        }
+    }
+}
+
+pub fn plan<N: Plannable>(
+    start: &N,
+    target: Pt2,
+    model: Cost,
+) -> Result<Vec<Instr>, String> {
+    if start.math() != Math::Exact {
+        return Err("plans need exact math".to_string());
+    }
+    // each state has the instruction that reached it and the state before
+    let mut states: Vec<(N, Option<(Instr, usize)>)> =
+        vec![(start.clone(), None)];
+    let mut best = HashMap::new();
+    best.insert(start.key(), (0, 0));
+    let mut queue = BinaryHeap::new();
+    queue.push(Reverse(((0, 0), 0)));
+    let mut found = None;
+    while let Some(Reverse((dist, id))) = queue.pop() {
+        let nav = states[id].0.clone();
+        if best[&nav.key()] < dist {
+            continue;
+        }
+        if nav.loc() == target {
+            found = Some(id);
+            break;
+        }
+        for instr in nav.candidates(target) {
+            let mut next = nav.clone();
+            if next.exec(&instr).is_err() {
+                continue;
+            }
+            let (a, b) = cost(model, nav.loc(), next.loc());
+            let next_dist = (dist.0 + a, dist.1 + b);
+            let key = next.key();
+            if best.get(&key).is_some_and(|&d| d <= next_dist) {
+                continue;
+            }
+            best.insert(key, next_dist);
+            states.push((next, Some((instr, id))));
+            queue.push(Reverse((next_dist, states.len() - 1)));
+        }
+    }
+
+    let mut id = found.ok_or("no plan reaches the target")?;
+    let mut instrs = Vec::new();
+    while let Some((instr, prev)) = states[id].1 {
+        instrs.push(instr);
+        id = prev;
+    }
+    instrs.reverse();
+
+    let mut nav = start.clone();
+    nav.run(&instrs)?;
+    if nav.loc() != target {
+        let end = nav.loc();
+        return Err(format!("plan ends at {:?}, not {:?}", end, target));
+    }
+    Ok(instrs)
+}
@@ -0,0 +1,216 @@
+// trajectory.rs: records where a ship goes and exports the trip as SVG or
+// GeoJSON. Both exports mark the start and end, and the Manhattan box: the
+// rectangle with the start and end at opposite corners, whose width plus
+// height is the Manhattan distance between them.
+
+use crate::{Instr, Navigator, Pt2};
+use std::fmt;
+
+// The state of a ship at some point in its trip. The heading is in degrees
+// clockwise from north, and the waypoint is absolute.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+    pub loc: Pt2,
+    pub heading: f64,
+    pub waypt: Option<Pt2>,
+}
+
+pub struct Trajectory {
+    // the start, then the state after each instruction
+    pub points: Vec<Point>,
+}
+
+fn point<N: Navigator>(nav: &N) -> Point {
+    Point {
+        loc: nav.loc(),
+        heading: nav.heading(),
+        waypt: nav.waypoint(),
+    }
+}
+
+pub fn record<N: Navigator>(
+    nav: &mut N,
+    instrs: &[Instr],
+) -> Result<Trajectory, String> {
+    let mut points = vec![point(nav)];
+    for (i, instr) in instrs.iter().enumerate() {
+        let err = |e| format!("instruction {}: {}", i + 1, e);
+        nav.exec(instr).map_err(err)?;
+        points.push(point(nav));
+    }
+    Ok(Trajectory { points })
+}
+
+impl Trajectory {
+    fn start(&self) -> Pt2 {
+        self.points[0].loc
+    }
+
+    fn end(&self) -> Pt2 {
+        self.points[self.points.len() - 1].loc
+    }
+
+    fn distance(&self) -> f64 {
+        let (start, end) = (self.start(), self.end());
+        (end.0 - start.0).abs() + (end.1 - start.1).abs()
+    }
+
+    // The corners of the Manhattan box, going around from the start.
+    fn manhattan_box(&self) -> [Pt2; 4] {
+        let (start, end) = (self.start(), self.end());
+        [start, Pt2(end.0, start.1), end, Pt2(start.0, end.1)]
+    }
+
+    // The smallest and largest coordinates of every location and waypoint.
+    fn bounds(&self) -> (Pt2, Pt2) {
+        let mut min = self.start();
+        let mut max = self.start();
+        let pts = self
+            .points
+            .iter()
+            .flat_map(|p| Some(p.loc).into_iter().chain(p.waypt));
+        for pt in pts {
+            min = Pt2(min.0.min(pt.0), min.1.min(pt.1));
+            max = Pt2(max.0.max(pt.0), max.1.max(pt.1));
+        }
+        (min, max)
+    }
+
+    // North is up, so y is flipped.
+    pub fn svg(&self) -> String {
+        // 0.0 - y rather than -y, so that 0 doesn't print as -0
+        let flip = |y: f64| 0.0 - y;
+        let (min, max) = self.bounds();
+        let size = (max.0 - min.0).max(max.1 - min.1).max(1.0);
+        let margin = size / 20.0;
+        let stroke = size / 500.0;
+        let xy = |pt: Pt2| format!("{},{}", pt.0, flip(pt.1));
+        let poly = |pts: Vec<Pt2>| {
+            pts.into_iter().map(xy).collect::<Vec<_>>().join(" ")
+        };
+        let view = format!(
+            "{} {} {} {}",
+            min.0 - margin,
+            flip(max.1) - margin,
+            max.0 - min.0 + 2.0 * margin,
+            max.1 - min.1 + 2.0 * margin,
+        );
+        let mut s = "<svg".to_string();
+        s += &attr("xmlns", "http://www.w3.org/2000/svg");
+        s += &attr("viewBox", view);
+        s += ">\n";
+
+        s += "  <polygon";
+        s += &attr("points", poly(self.manhattan_box().to_vec()));
+        s += &attr("fill", "none");
+        s += &attr("stroke", "gray");
+        s += &attr("stroke-width", stroke);
+        s += &attr("stroke-dasharray", stroke * 4.0);
+        s += &format!("><title>Manhattan distance {}</title>", self.distance());
+        s += "</polygon>\n";
+
+        let waypts: Vec<_> =
+            self.points.iter().filter_map(|p| p.waypt).collect();
+        if !waypts.is_empty() {
+            s += "  <polyline";
+            s += &attr("points", poly(waypts));
+            s += &attr("fill", "none");
+            s += &attr("stroke", "orange");
+            s += &attr("stroke-width", stroke);
+            s += "/>\n";
+        }
+
+        s += "  <polyline";
+        s += &attr("points", poly(self.points.iter().map(|p| p.loc).collect()));
+        s += &attr("fill", "none");
+        s += &attr("stroke", "blue");
+        s += &attr("stroke-width", stroke * 2.0);
+        s += "/>\n";
+
+        for (name, pt, color) in
+            &[("start", self.start(), "green"), ("end", self.end(), "red")]
+        {
+            s += "  <circle";
+            s += &attr("cx", pt.0);
+            s += &attr("cy", flip(pt.1));
+            s += &attr("r", stroke * 5.0);
+            s += &attr("fill", color);
+            s += &format!("><title>{} {}</title>", name, xy(*pt));
+            s += "</circle>\n";
+        }
+
+        s += "  <text";
+        s += &attr("x", min.0);
+        s += &attr("y", flip(max.1) - margin / 3.0);
+        s += &attr("font-size", margin / 2.0);
+        s += &format!(">Manhattan distance {}</text>\n", self.distance());
+        s + "</svg>\n"
+    }
+
+    pub fn geojson(&self) -> String {
+        let headings: Vec<_> =
+            self.points.iter().map(|p| p.heading.to_string()).collect();
+        let locs = self.points.iter().map(|p| p.loc).collect();
+        let mut features = vec![feature(
+            geometry("LineString", json_pts(locs)),
+            format!(
+                "\"name\": \"trajectory\", \"headings\": [{}]",
+                headings.join(", ")
+            ),
+        )];
+        let waypts: Vec<_> =
+            self.points.iter().filter_map(|p| p.waypt).collect();
+        if !waypts.is_empty() {
+            features.push(feature(
+                geometry("LineString", json_pts(waypts)),
+                "\"name\": \"waypoint\"".to_string(),
+            ));
+        }
+        for (name, pt) in &[("start", self.start()), ("end", self.end())] {
+            features.push(feature(
+                geometry("Point", json_pt(*pt)),
+                format!("\"name\": \"{}\"", name),
+            ));
+        }
+        let mut corners = self.manhattan_box().to_vec();
+        corners.push(corners[0]);
+        features.push(feature(
+            geometry("Polygon", format!("[{}]", json_pts(corners))),
+            format!(
+                "\"name\": \"manhattan box\", \"distance\": {}",
+                self.distance()
+            ),
+        ));
+        let mut s = "{\n".to_string();
+        s += "  \"type\": \"FeatureCollection\",\n";
+        s += "  \"features\": [\n";
+        s += &features.join(",\n");
+        s + "\n  ]\n}\n"
+    }
+}
+
+// An SVG attribute, with a leading space.
+fn attr(name: &str, value: impl fmt::Display) -> String {
+    format!(" {}=\"{}\"", name, value)
+}
+
+fn json_pt(pt: Pt2) -> String {
+    format!("[{}, {}]", pt.0, pt.1)
+}
+
+fn json_pts(pts: Vec<Pt2>) -> String {
+    let pts: Vec<_> = pts.into_iter().map(json_pt).collect();
+    format!("[{}]", pts.join(", "))
+}
+
+fn geometry(kind: &str, coords: String) -> String {
+    format!("{{\"type\": \"{}\", \"coordinates\": {}}}", kind, coords)
+}
+
+// One line of the feature collection.
+fn feature(geometry: String, properties: String) -> String {
+    let mut s = "    {\"type\": \"Feature\"".to_string();
+    s += &format!(", \"geometry\": {}", geometry);
+    s += &format!(", \"properties\": {{{}}}}}", properties);
+    s
+}
@@ -1,6 +1,28 @@
-// crt.rs: copied from rosetta code:
-// https://rosettacode.org/wiki/Chinese_remainder_theorem#Rust
-fn egcd(a: i64, b: i64) -> (i64, i64, i64) {
+// crt.rs: the Chinese remainder theorem for moduli that need not be
+// pairwise coprime. Congruences are merged two at a time into a single
+// congruence modulo the lcm of their moduli, which fails if they disagree
+// modulo the gcd. Arithmetic is in i128 and checked, so an lcm too big to
+// represent is an error rather than a wrong answer.
+
+// x ≡ residue (mod modulus), with 0 <= residue < modulus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Congruence {
+    pub residue: i128,
+    pub modulus: i128,
+}
+
+impl Congruence {
+    pub fn new(residue: i128, modulus: i128) -> Result<Self, String> {
+        if modulus <= 0 {
+            return Err(format!("bad modulus: {}", modulus));
+        }
+        let residue = residue.rem_euclid(modulus);
+        Ok(Congruence { residue, modulus })
+    }
+}
+
+// Returns (g, x, y) such that a * x + b * y = g = gcd(a, b).
+fn egcd(a: i128, b: i128) -> (i128, i128, i128) {
     if a == 0 {
         (b, 0, 1)
     } else {
@@ -9,24 +31,43 @@ fn egcd(a: i64, b: i64) -> (i64, i64, i64) {
     }
 }
 
-fn mod_inv(x: i64, n: i64) -> Option<i64> {
-    let (g, x, _) = egcd(x, n);
-    if g == 1 {
-        Some((x % n + n) % n)
-    } else {
-        None
+// a * b % m for 0 <= a, b < m, without overflowing.
+fn mul_mod(a: i128, b: i128, m: i128) -> i128 {
+    let (mut a, mut b, m) = (a as u128, b as u128, m as u128);
+    let mut product = 0;
+    while b > 0 {
+        if b & 1 == 1 {
+            product = (product + a) % m;
+        }
+        a = (a << 1) % m;
+        b >>= 1;
     }
+    product as i128
 }
 
-pub fn chinese_remainder(residues: &[i64], modulii: &[i64]) -> Option<i64> {
-    let prod = modulii.iter().product::<i64>();
-
-    let mut sum = 0;
-
-    for (&residue, &modulus) in residues.iter().zip(modulii) {
-        let p = prod / modulus;
-        sum += residue * mod_inv(p, modulus)? * p
+pub fn merge(a: Congruence, b: Congruence) -> Result<Congruence, String> {
+    let (g, p, _) = egcd(a.modulus, b.modulus);
+    let diff = b.residue - a.residue;
+    if diff % g != 0 {
+        return Err(format!(
+            "x ≡ {} (mod {}) and x ≡ {} (mod {}) disagree modulo {}",
+            a.residue, a.modulus, b.residue, b.modulus, g
+        ));
     }
+    // x = a.residue + a.modulus * k, where k solves
+    // a.modulus / g * k ≡ diff / g (mod b.modulus / g)
+    let m = b.modulus / g;
+    let lcm = a.modulus.checked_mul(m).ok_or_else(|| {
+        format!("lcm of {} and {} overflows", a.modulus, b.modulus)
+    })?;
+    let k = mul_mod((diff / g).rem_euclid(m), p.rem_euclid(m), m);
+    let residue = (a.residue + a.modulus * k) % lcm;
+    Congruence::new(residue, lcm)
+}
 
-    Some(sum % prod)
+// Solves the whole system, returning the congruence that describes every
+// solution.
+pub fn solve(congruences: &[Congruence]) -> Result<Congruence, String> {
+    let all = Congruence::new(0, 1)?;
+    congruences.iter().try_fold(all, |x, &c| merge(x, c))
 }
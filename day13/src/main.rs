@@ -1,7 +1,7 @@
 mod crt;
 
 fn atoi(s: &str) -> i64 {
-    i64::from_str_radix(s, 10).unwrap()
+    s.parse().unwrap()
 }
 
 fn wait_time(from: i64, bus: i64) -> i64 {
@@ -20,24 +20,29 @@ fn first_arrival(from: i64, buses: &[&str]) -> (i64, i64) {
     arrivals[0]
 }
 
-fn sequence_timestamp(buses: &[&str]) -> i64 {
-    let reqs: Vec<(i64, i64)> = buses
-        .iter()
-        .enumerate()
-        .filter(|(_, s)| s != &&"x")
-        .map(|(i, s)| (i as i64, atoi(s)))
-        .map(|(i, n)| ((n * 100 - i) % n, n))
-        .collect();
-    // for each (a, n) in reqs, we want x s.t. x = a (mod n)
-    let residues: Vec<_> = reqs.iter().map(|(i, _)| *i).collect();
-    let moduli: Vec<_> = reqs.iter().map(|(_, n)| *n).collect();
-    let n = crt::chinese_remainder(&residues, &moduli).unwrap();
-    n
+// The earliest timestamp at which each bus leaves as many minutes after it
+// as its offset in the list.
+fn sequence_timestamp(buses: &[&str]) -> Result<i128, String> {
+    let mut reqs = Vec::new();
+    for (i, &bus) in buses.iter().enumerate().filter(|(_, s)| s != &&"x") {
+        let n: i128 = bus.parse().map_err(|_| format!("bad bus: {}", bus))?;
+        let req = crt::Congruence::new(-(i as i128), n);
+        reqs.push(req.map_err(|_| format!("bad bus: {}", bus))?);
+    }
+    crt::solve(&reqs).map(|x| x.residue)
 }
 
 fn main() {
-    let path = std::env::args().nth(1).unwrap();
-    let text = std::fs::read_to_string(&path).unwrap();
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    if args[0] == "schedule" {
+        let buses: Vec<_> = args[1].split(',').collect();
+        match sequence_timestamp(&buses) {
+            Ok(t) => println!("{}", t),
+            Err(e) => println!("no timestamp: {}", e),
+        }
+        return;
+    }
+    let text = std::fs::read_to_string(&args[0]).unwrap();
     let lines: Vec<_> = text.lines().collect();
     let earliest = atoi(lines[0]);
     let buses: Vec<_> = lines[1].split(',').collect();
@@ -45,5 +50,5 @@ fn main() {
     let (bus, wait) = first_arrival(earliest, &buses);
     println!("{}", bus * wait);
 
-    println!("{}", sequence_timestamp(&buses));
+    println!("{}", sequence_timestamp(&buses).unwrap());
 }
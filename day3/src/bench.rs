@@ -0,0 +1,112 @@
+// bench.rs: compares the BitGrid against the old HashMap-backed grid on
+// large generated forests. Run with `cargo run --release bench`.
+
+use crate::bitgrid::BitGrid;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+struct HashGrid {
+    tiles: HashMap<(i64, i64), bool>,
+    width: i64,
+    height: i64,
+}
+
+impl HashGrid {
+    fn trees(&self, dx: i64, dy: i64) -> usize {
+        let (mut x, mut y, mut n) = (0, 0, 0);
+        while y < self.height {
+            if self.tiles[&(x % self.width, y)] {
+                n += 1;
+            }
+            x += dx;
+            y += dy;
+        }
+        n
+    }
+}
+
+fn bit_trees(g: &BitGrid, dx: i64, dy: i64) -> usize {
+    let (mut x, mut y, mut n) = (0, 0, 0);
+    while y < g.height() as i64 {
+        if g.at(x, y) == Some(true) {
+            n += 1;
+        }
+        x += dx;
+        y += dy;
+    }
+    n
+}
+
+// A forest with a tree on about one tile in four. It only depends on the
+// seed, so every run times the same walks.
+fn forest(width: usize, height: usize, seed: u64) -> Vec<Vec<bool>> {
+    let mut state = seed;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    (0..height)
+        .map(|_| (0..width).map(|_| next() % 4 == 0).collect())
+        .collect()
+}
+
+fn time<T>(f: impl Fn() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let result = f();
+    (result, start.elapsed())
+}
+
+const SLOPES: [(i64, i64); 5] = [(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)];
+
+pub fn run() {
+    for &(width, height) in &[(31, 10_000), (256, 100_000), (1024, 100_000)] {
+        let cells = forest(width, height, 0x2020_1203);
+        let mut bits = BitGrid::new(width, height);
+        let mut tiles = HashMap::new();
+        for (y, row) in cells.iter().enumerate() {
+            for (x, &tree) in row.iter().enumerate() {
+                bits.set(x, y, tree);
+                tiles.insert((x as i64, y as i64), tree);
+            }
+        }
+        let hash = HashGrid {
+            tiles,
+            width: width as i64,
+            height: height as i64,
+        };
+
+        let (expected, hash_time) = time(|| {
+            SLOPES
+                .iter()
+                .map(|&(dx, dy)| hash.trees(dx, dy))
+                .collect::<Vec<_>>()
+        });
+        let (actual, bit_time) = time(|| {
+            SLOPES
+                .iter()
+                .map(|&(dx, dy)| bit_trees(&bits, dx, dy))
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(expected, actual);
+
+        // walk the same forest as a torus for a fixed number of steps
+        let torus = bits.clone().wrap_y(true);
+        let (_, torus_time) = time(|| {
+            (0..height as i64 * 4)
+                .filter(|&i| torus.at(i * 3, i).unwrap())
+                .count()
+        });
+
+        println!(
+            "{}x{}: hashmap {:?}, bitgrid {:?} ({:.1}x), torus {:?}",
+            bits.width(),
+            bits.height(),
+            hash_time,
+            bit_time,
+            hash_time.as_secs_f64() / bit_time.as_secs_f64(),
+            torus_time,
+        );
+    }
+}
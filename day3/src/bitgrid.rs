@@ -0,0 +1,75 @@
+// bitgrid.rs: a dense, row-major grid of bits. Lookups always wrap around
+// in x and optionally wrap around in y.
+
+const WORD_BITS: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitGrid {
+    words: Vec<u64>,
+    width: usize,
+    height: usize,
+    wrap_y: bool,
+}
+
+impl BitGrid {
+    pub fn new(width: usize, height: usize) -> Self {
+        let len = (width * height).div_ceil(WORD_BITS);
+        BitGrid {
+            words: vec![0; len],
+            width,
+            height,
+            wrap_y: false,
+        }
+    }
+
+    // With wrapping in y, at never returns None on a non-empty grid, so a
+    // walk that stops on None has to bound itself.
+    pub fn wrap_y(mut self, wrap: bool) -> Self {
+        self.wrap_y = wrap;
+        self
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn index(&self, x: usize, y: usize) -> (usize, u64) {
+        assert!(x < self.width && y < self.height, "out of bounds");
+        let i = y * self.width + x;
+        (i / WORD_BITS, 1 << (i % WORD_BITS))
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, value: bool) {
+        let (word, mask) = self.index(x, y);
+        if value {
+            self.words[word] |= mask;
+        } else {
+            self.words[word] &= !mask;
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        let (word, mask) = self.index(x, y);
+        self.words[word] & mask != 0
+    }
+
+    // Returns None if y is out of range and the grid doesn't wrap in y.
+    pub fn at(&self, x: i64, y: i64) -> Option<bool> {
+        if self.width == 0 || self.height == 0 {
+            return None;
+        }
+        let x = x.rem_euclid(self.width as i64) as usize;
+        let y = if self.wrap_y {
+            y.rem_euclid(self.height as i64) as usize
+        } else if y >= 0 && (y as usize) < self.height {
+            y as usize
+        } else {
+            return None;
+        };
+        Some(self.get(x, y))
+    }
+}
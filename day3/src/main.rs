@@ -1,10 +1,7 @@
-use std::collections::HashMap;
+mod bench;
+mod bitgrid;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Tile {
-    Tree,
-    Empty,
-}
+use bitgrid::BitGrid;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct Pt2 {
@@ -21,45 +18,31 @@ impl Pt2 {
     }
 }
 
-#[derive(Debug)]
-struct Grid {
-    tiles: HashMap<Pt2, Tile>,
-    width: i64,
-    height: i64,
-}
-
-impl Grid {
-    fn at(&self, pt: &Pt2) -> Tile {
-        let (x, y) = (pt.x % self.width, pt.y);
-        self.tiles[&Pt2 { x, y }]
-    }
-}
-
-fn parse(s: &str) -> Result<Grid, String> {
-    let mut tiles = HashMap::new();
-    let (mut width, mut height) = (0, 0);
-    for (y, line) in s.lines().enumerate() {
-        width = 0;
+fn parse(s: &str) -> Result<BitGrid, String> {
+    let lines: Vec<_> = s.lines().collect();
+    let width = lines.first().map_or(0, |line| line.len());
+    let mut grid = BitGrid::new(width, lines.len());
+    for (y, line) in lines.iter().enumerate() {
+        if line.len() != width {
+            return Err(format!("bad row length at {}: {}", y, line.len()));
+        }
         for (x, ch) in line.chars().enumerate() {
-            let pt = Pt2 { x: x as i64, y: y as i64 };
-            let tile = match ch {
-                '.' => Tile::Empty,
-                '#' => Tile::Tree,
+            match ch {
+                '.' => {}
+                '#' => grid.set(x, y, true),
                 ch => return Err(format!("bad tile at {}, {}: {}", x, y, ch)),
             };
-            tiles.insert(pt, tile);
-            width += 1;
         }
-        height += 1;
     }
-    Ok(Grid { tiles, width, height })
+    Ok(grid)
 }
 
-fn trees(g: &Grid, slope: &Pt2) -> usize {
+fn trees(g: &BitGrid, slope: &Pt2) -> usize {
     let mut p = Pt2 { x: 0, y: 0 };
     let mut n = 0;
-    while p.y < g.height {
-        if g.at(&p) == Tile::Tree {
+    // stop at the bottom even if the grid wraps in y
+    while p.y < g.height() as i64 {
+        if g.at(p.x, p.y) == Some(true) {
             n += 1;
         }
         p = p.add(slope);
@@ -69,11 +52,15 @@ fn trees(g: &Grid, slope: &Pt2) -> usize {
 
 fn main() {
     let path = std::env::args().nth(1).unwrap();
+    if path == "bench" {
+        bench::run();
+        return;
+    }
     let text = std::fs::read_to_string(&path).unwrap();
     let grid = parse(&text).unwrap();
     println!("{}", trees(&grid, &Pt2 { x: 3, y: 1 }));
-    
-    let slopes = vec![
+
+    let slopes = [
         Pt2 { x: 1, y: 1 },
         Pt2 { x: 3, y: 1 },
         Pt2 { x: 5, y: 1 },
@@ -0,0 +1,225 @@
+// formats.rs: converts passport batches to and from JSON Lines and CSV.
+// Fields are always written in COLUMNS order, followed by any other keys
+// in sorted order, so that every format round-trips to the same text.
+
+use crate::{parse_entries, Entry};
+use serde::de::{Deserialize, Deserializer, Error, MapAccess, Visitor};
+use std::fmt;
+
+const COLUMNS: [&str; 8] =
+    ["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid", "cid"];
+
+pub type Record = Vec<(String, String)>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Passports,
+    JsonLines,
+    Csv,
+}
+
+impl Format {
+    pub fn parse(s: &str) -> Result<Format, String> {
+        match s {
+            "passports" => Ok(Format::Passports),
+            "jsonl" => Ok(Format::JsonLines),
+            "csv" => Ok(Format::Csv),
+            s => Err(format!("bad format: {}", s)),
+        }
+    }
+
+    pub fn read(self, s: &str) -> Result<Vec<Record>, String> {
+        match self {
+            Format::Passports => {
+                Ok(parse_entries(s)?.iter().map(record).collect())
+            }
+            Format::JsonLines => from_jsonl(s),
+            Format::Csv => from_csv(s),
+        }
+    }
+
+    pub fn write(self, records: &[Record]) -> String {
+        match self {
+            Format::Passports => to_passports(records),
+            Format::JsonLines => to_jsonl(records),
+            Format::Csv => to_csv(records),
+        }
+    }
+}
+
+fn column_order(k: &str) -> (usize, &str) {
+    let i = COLUMNS.iter().position(|c| *c == k);
+    (i.unwrap_or(COLUMNS.len()), k)
+}
+
+fn canonicalize(mut r: Record) -> Record {
+    r.sort_by(|(a, _), (b, _)| column_order(a).cmp(&column_order(b)));
+    r
+}
+
+fn record(e: &Entry) -> Record {
+    let r = e.iter().map(|(k, v)| (k.to_string(), v.to_string()));
+    canonicalize(r.collect())
+}
+
+fn columns(records: &[Record]) -> Vec<String> {
+    let mut cols: Vec<_> = records.iter().flatten().map(|(k, _)| k).collect();
+    cols.sort_by_key(|k| column_order(k));
+    cols.dedup();
+    cols.into_iter().cloned().collect()
+}
+
+// Adds a field, rejecting duplicate keys and the empty values that the
+// passport and CSV formats can't represent.
+fn push_field(r: &mut Record, k: String, v: String) -> Result<(), String> {
+    if k.is_empty() || v.is_empty() || k.contains(':') {
+        return Err(format!("bad field: {:?}: {:?}", k, v));
+    }
+    if k.contains(char::is_whitespace) || v.contains(char::is_whitespace) {
+        return Err(format!("whitespace in field: {:?}: {:?}", k, v));
+    }
+    if r.iter().any(|(k2, _)| *k2 == k) {
+        return Err(format!("duplicate key: {}", k));
+    }
+    r.push((k, v));
+    Ok(())
+}
+
+fn to_passports(records: &[Record]) -> String {
+    let lines: Vec<_> = records
+        .iter()
+        .map(|r| {
+            let fields: Vec<_> =
+                r.iter().map(|(k, v)| format!("{}:{}", k, v)).collect();
+            fields.join(" ") + "\n"
+        })
+        .collect();
+    lines.join("\n")
+}
+
+fn to_jsonl(records: &[Record]) -> String {
+    let quote = |s: &str| serde_json::to_string(s).unwrap();
+    let mut out = String::new();
+    for r in records {
+        let fields: Vec<_> = r
+            .iter()
+            .map(|(k, v)| format!("{}:{}", quote(k), quote(v)))
+            .collect();
+        out += &format!("{{{}}}\n", fields.join(","));
+    }
+    out
+}
+
+struct JsonRecord(Record);
+
+impl<'de> Deserialize<'de> for JsonRecord {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        struct RecordVisitor;
+
+        impl<'de> Visitor<'de> for RecordVisitor {
+            type Value = JsonRecord;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "an object of string fields")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> Result<JsonRecord, A::Error> {
+                let mut r = Record::new();
+                while let Some((k, v)) = map.next_entry()? {
+                    push_field(&mut r, k, v).map_err(A::Error::custom)?;
+                }
+                Ok(JsonRecord(r))
+            }
+        }
+
+        d.deserialize_map(RecordVisitor)
+    }
+}
+
+fn from_jsonl(s: &str) -> Result<Vec<Record>, String> {
+    let mut records = Vec::new();
+    for (i, line) in s.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let r: JsonRecord = serde_json::from_str(line)
+            .map_err(|e| format!("line {}: {}", i + 1, e))?;
+        records.push(canonicalize(r.0));
+    }
+    Ok(records)
+}
+
+fn csv_quote(s: &str) -> String {
+    if s.contains([',', '"']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn csv_split(line: &str) -> Result<Vec<String>, String> {
+    let mut cells = vec![String::new()];
+    let mut chars = line.chars().peekable();
+    let mut quoted = false;
+    while let Some(ch) = chars.next() {
+        let cell = cells.last_mut().unwrap();
+        match ch {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                cell.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if cell.is_empty() => quoted = true,
+            ',' if !quoted => cells.push(String::new()),
+            ch => cell.push(ch),
+        }
+    }
+    if quoted {
+        return Err("unterminated quote".to_string());
+    }
+    Ok(cells)
+}
+
+fn to_csv(records: &[Record]) -> String {
+    let cols = columns(records);
+    let header: Vec<_> = cols.iter().map(|c| csv_quote(c)).collect();
+    let mut out = header.join(",") + "\n";
+    for r in records {
+        let row: Vec<_> = cols
+            .iter()
+            .map(|c| r.iter().find(|(k, _)| k == c).map(|(_, v)| v))
+            .map(|v| v.map_or(String::new(), |v| csv_quote(v)))
+            .collect();
+        out += &(row.join(",") + "\n");
+    }
+    out
+}
+
+fn from_csv(s: &str) -> Result<Vec<Record>, String> {
+    let mut lines = s.lines().enumerate();
+    let header = match lines.next() {
+        Some((_, line)) => {
+            csv_split(line).map_err(|e| format!("line 1: {}", e))?
+        }
+        None => return Ok(Vec::new()),
+    };
+    let mut records = Vec::new();
+    for (i, line) in lines {
+        let err = |e| format!("line {}: {}", i + 1, e);
+        let cells = csv_split(line).map_err(err)?;
+        if cells.len() != header.len() {
+            return Err(err(format!("expected {} cells", header.len())));
+        }
+        let mut r = Record::new();
+        for (k, v) in header.iter().zip(cells) {
+            if !v.is_empty() {
+                push_field(&mut r, k.clone(), v).map_err(err)?;
+            }
+        }
+        records.push(canonicalize(r));
+    }
+    Ok(records)
+}
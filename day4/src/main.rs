@@ -1,80 +1,83 @@
-use regex::Regex;
+mod formats;
+mod schema;
+
+use formats::Format;
+use schema::Schema;
 use std::collections::HashMap;
 
 type Entry<'t> = HashMap<&'t str, &'t str>;
 
-fn parse_entries(s: &str) -> Vec<Entry> {
+fn parse_entries(s: &str) -> Result<Vec<Entry<'_>>, String> {
     let mut entries = Vec::new();
-    for line in s.split("\n\n") {
+    for (i, line) in s.split("\n\n").enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
         let mut m = HashMap::new();
         for segment in line.split_ascii_whitespace() {
-            let mut toks = segment.split(':');
-            let k = toks.next().unwrap();
-            let v = toks.next().unwrap();
-            m.insert(k, v);
+            let (k, v) = match segment.split_once(':') {
+                Some((k, v)) if !k.is_empty() && !v.is_empty() => (k, v),
+                _ => {
+                    return Err(format!(
+                        "entry {}: bad segment: {}",
+                        i, segment
+                    ))
+                }
+            };
+            if m.insert(k, v).is_some() {
+                return Err(format!("entry {}: duplicate key: {}", i, k));
+            }
         }
         entries.push(m);
     }
-    entries
+    Ok(entries)
 }
 
-fn has_fields(e: &Entry) -> bool {
-    e.len() == 8 || (e.len() == 7 && !e.contains_key("cid"))
-}
-
-fn atoi(s: &str) -> Option<i32> {
-    i32::from_str_radix(s, 10).ok()
-}
+const DEFAULT_SCHEMA: &str = include_str!("../schema.toml");
 
-struct Validator {
-    res: HashMap<&'static str, Regex>,
+// usage: convert <from> <to> <path>, where the formats are passports,
+// jsonl, and csv
+fn convert(args: &[String]) -> Result<String, String> {
+    let from = Format::parse(&args[0])?;
+    let to = Format::parse(&args[1])?;
+    let text = std::fs::read_to_string(&args[2]).map_err(|e| e.to_string())?;
+    Ok(to.write(&from.read(&text)?))
 }
 
-impl Validator {
-    fn new() -> Self {
-        let mut res = HashMap::new();
-        let regex = |s| Regex::new(s).unwrap();
-        res.insert("hgt", regex(r"^(\d+)(cm|in)$"));
-        res.insert("hcl", regex(r"^#[0-9a-f]{6}$"));
-        res.insert("ecl", regex(r"^(amb|blu|brn|gry|grn|hzl|oth)$"));
-        res.insert("pid", regex(r"^[0-9]{9}$"));
-        Self { res }
+fn main() {
+    let mut args: Vec<_> = std::env::args().skip(1).collect();
+    if args[0] == "convert" {
+        print!("{}", convert(&args[1..]).unwrap());
+        return;
+    }
+    let report = args.first().is_some_and(|arg| arg == "report");
+    if report {
+        args.remove(0);
     }
+    let text = std::fs::read_to_string(&args[0]).unwrap();
+    let schema = match args.get(1) {
+        Some(path) => std::fs::read_to_string(path).unwrap(),
+        None => DEFAULT_SCHEMA.to_string(),
+    };
+    let schema = Schema::parse(&schema).unwrap();
+    let entries = parse_entries(&text).unwrap();
+    let errs: Vec<_> = entries.iter().map(|e| schema.validate(e)).collect();
 
-    fn is_valid(&self, e: &Entry) -> bool {
-        let valid_fields = e
-            .iter()
-            .filter(|(&k, _)| k != "cid")
-            .filter(|(&k, v)| match k {
-                "byr" => atoi(v).map_or(false, |n| n >= 1920 && n <= 2002),
-                "iyr" => atoi(v).map_or(false, |n| n >= 2010 && n <= 2020),
-                "eyr" => atoi(v).map_or(false, |n| n >= 2020 && n <= 2030),
-                "hgt" => match self.res[k].captures(v) {
-                    Some(caps) if caps.get(2).unwrap().as_str() == "cm" => {
-                        let n = atoi(caps.get(1).unwrap().as_str());
-                        n.map_or(false, |n| n >= 150 && n <= 193)
-                    }
-                    Some(caps) if caps.get(2).unwrap().as_str() == "in" => {
-                        let n = atoi(caps.get(1).unwrap().as_str());
-                        n.map_or(false, |n| n >= 59 && n <= 76)
-                    }
-                    _ => false,
-                },
-                k => self.res[k].is_match(v),
-            })
-            .count();
-        return has_fields(e) && valid_fields == 7;
+    if report {
+        for (i, errs) in errs.iter().enumerate() {
+            for err in errs {
+                println!("passport {}: {}", i, err);
+            }
+        }
+        return;
     }
-}
 
-fn main() {
-    let path = std::env::args().nth(1).unwrap();
-    let text = std::fs::read_to_string(&path).unwrap();
-    let entries = parse_entries(&text);
-    let num_complete = entries.iter().filter(|e| has_fields(e)).count();
+    let num_complete = errs
+        .iter()
+        .filter(|errs| !errs.iter().any(|e| e.is_missing()))
+        .count();
     println!("{}", num_complete);
 
-    let v = Validator::new();
-    let num_valid = entries.iter().filter(|e| v.is_valid(e)).count();
+    let num_valid = errs.iter().filter(|errs| errs.is_empty()).count();
     println!("{}", num_valid);
 }
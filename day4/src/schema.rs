@@ -0,0 +1,164 @@
+// schema.rs: declarative passport schemas, loaded from TOML. See
+// schema.toml for the rules from the puzzle.
+
+use crate::Entry;
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Kind {
+    Any,
+    Int { min: i64, max: i64 },
+    Regex { pattern: String },
+    Enum { values: Vec<String> },
+    Quantity { units: BTreeMap<String, (i64, i64)> },
+}
+
+fn required_default() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+struct FieldSpec {
+    #[serde(flatten)]
+    kind: Kind,
+    #[serde(default = "required_default")]
+    required: bool,
+    message: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SchemaSpec {
+    fields: BTreeMap<String, FieldSpec>,
+}
+
+enum Check {
+    Any,
+    Int(i64, i64),
+    Regex(Regex),
+    Enum(Vec<String>),
+    Quantity(BTreeMap<String, (i64, i64)>),
+}
+
+impl Check {
+    fn matches(&self, v: &str) -> bool {
+        let in_range = |n: &str, (min, max): (i64, i64)| {
+            n.parse().is_ok_and(|n: i64| n >= min && n <= max)
+        };
+        match self {
+            Check::Any => true,
+            Check::Int(min, max) => in_range(v, (*min, *max)),
+            Check::Regex(re) => re.is_match(v),
+            Check::Enum(values) => values.iter().any(|x| x == v),
+            Check::Quantity(units) => {
+                let i =
+                    v.find(|c: char| !c.is_ascii_digit()).unwrap_or(v.len());
+                let (n, unit) = v.split_at(i);
+                units.get(unit).is_some_and(|&range| in_range(n, range))
+            }
+        }
+    }
+}
+
+struct Field {
+    name: String,
+    required: bool,
+    check: Check,
+    message: String,
+}
+
+pub struct Schema {
+    fields: Vec<Field>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldError {
+    Missing(String),
+    Unknown(String),
+    Invalid {
+        field: String,
+        value: String,
+        message: String,
+    },
+}
+
+impl FieldError {
+    pub fn is_missing(&self) -> bool {
+        matches!(self, FieldError::Missing(_))
+    }
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FieldError::Missing(field) => write!(f, "{}: missing", field),
+            FieldError::Unknown(field) => write!(f, "{}: unknown field", field),
+            FieldError::Invalid {
+                field,
+                value,
+                message,
+            } => write!(f, "{}: {:?}: {}", field, value, message),
+        }
+    }
+}
+
+impl Schema {
+    pub fn parse(s: &str) -> Result<Schema, String> {
+        let spec: SchemaSpec = toml::from_str(s).map_err(|e| e.to_string())?;
+        let mut fields = Vec::new();
+        for (name, field) in spec.fields {
+            let check = match field.kind {
+                Kind::Any => Check::Any,
+                Kind::Int { min, max } => Check::Int(min, max),
+                Kind::Regex { pattern } => match Regex::new(&pattern) {
+                    Ok(re) => Check::Regex(re),
+                    Err(e) => {
+                        return Err(format!("bad regex for {}: {}", name, e))
+                    }
+                },
+                Kind::Enum { values } => Check::Enum(values),
+                Kind::Quantity { units } => Check::Quantity(units),
+            };
+            let message =
+                field.message.unwrap_or_else(|| "invalid value".to_string());
+            fields.push(Field {
+                name,
+                required: field.required,
+                check,
+                message,
+            });
+        }
+        Ok(Schema { fields })
+    }
+
+    pub fn validate(&self, e: &Entry) -> Vec<FieldError> {
+        let mut errs = Vec::new();
+        for field in &self.fields {
+            match e.get(field.name.as_str()) {
+                None if field.required => {
+                    errs.push(FieldError::Missing(field.name.clone()))
+                }
+                Some(v) if !field.check.matches(v) => {
+                    errs.push(FieldError::Invalid {
+                        field: field.name.clone(),
+                        value: v.to_string(),
+                        message: field.message.clone(),
+                    })
+                }
+                _ => {}
+            }
+        }
+        let mut unknown: Vec<_> = e
+            .keys()
+            .filter(|k| self.fields.iter().all(|f| f.name != **k))
+            .collect();
+        unknown.sort();
+        for k in unknown {
+            errs.push(FieldError::Unknown(k.to_string()));
+        }
+        errs
+    }
+}
@@ -0,0 +1,90 @@
+// codec.rs: boarding passes are a binary row number followed by a binary
+// column number, each written with its own pair of (zero, one) letters.
+
+pub type Seat = (i32, i32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeatCodec {
+    row_bits: u32,
+    col_bits: u32,
+    row_letters: (char, char),
+    col_letters: (char, char),
+}
+
+impl SeatCodec {
+    pub fn new(
+        row_bits: u32,
+        col_bits: u32,
+        row_letters: (char, char),
+        col_letters: (char, char),
+    ) -> Result<Self, String> {
+        if row_bits + col_bits > 30 {
+            return Err(format!("too many bits: {}", row_bits + col_bits));
+        }
+        let (r0, r1) = row_letters;
+        let (c0, c1) = col_letters;
+        if r0 == r1 || c0 == c1 {
+            return Err("zero and one letters must differ".to_string());
+        }
+        Ok(SeatCodec {
+            row_bits,
+            col_bits,
+            row_letters,
+            col_letters,
+        })
+    }
+
+    pub fn rows(&self) -> i32 {
+        1 << self.row_bits
+    }
+
+    pub fn cols(&self) -> i32 {
+        1 << self.col_bits
+    }
+
+    pub fn seat_id(&self, (row, col): Seat) -> i32 {
+        row * self.cols() + col
+    }
+
+    fn decode_bits(s: &str, (zero, one): (char, char)) -> Result<i32, String> {
+        s.chars().try_fold(0, |n, ch| match ch {
+            ch if ch == zero => Ok(n << 1),
+            ch if ch == one => Ok((n << 1) | 1),
+            ch => Err(format!("bad letter: {}", ch)),
+        })
+    }
+
+    fn encode_bits(n: i32, bits: u32, (zero, one): (char, char)) -> String {
+        let bit = |i| if (n >> i) & 1 == 1 { one } else { zero };
+        (0..bits).rev().map(bit).collect()
+    }
+
+    pub fn decode(&self, s: &str) -> Result<Seat, String> {
+        let len = (self.row_bits + self.col_bits) as usize;
+        if s.chars().count() != len {
+            return Err(format!("bad pass {:?}: expected {} letters", s, len));
+        }
+        let split = s.char_indices().nth(self.row_bits as usize);
+        let (rows, cols) = s.split_at(split.map_or(s.len(), |(i, _)| i));
+        let err = |e| format!("bad pass {:?}: {}", s, e);
+        let row = Self::decode_bits(rows, self.row_letters).map_err(err)?;
+        let col = Self::decode_bits(cols, self.col_letters).map_err(err)?;
+        Ok((row, col))
+    }
+
+    pub fn encode(&self, (row, col): Seat) -> Result<String, String> {
+        let in_range = |n, max| (0..max).contains(&n);
+        if !in_range(row, self.rows()) || !in_range(col, self.cols()) {
+            return Err(format!("seat out of range: {:?}", (row, col)));
+        }
+        let rows = Self::encode_bits(row, self.row_bits, self.row_letters);
+        let cols = Self::encode_bits(col, self.col_bits, self.col_letters);
+        Ok(rows + &cols)
+    }
+}
+
+impl Default for SeatCodec {
+    fn default() -> Self {
+        SeatCodec::new(7, 3, ('F', 'B'), ('L', 'R')).unwrap()
+    }
+}
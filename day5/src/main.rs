@@ -1,20 +1,46 @@
-type Seat = (i32, i32);
+mod codec;
+mod seatmap;
 
-fn parse_seat(s: &str) -> Seat {
-    let btoi = |s| i32::from_str_radix(s, 2).unwrap();
-    let rowb = &s[..7].replace('B', "1").replace('F', "0");
-    let colb = &s[7..].replace('R', "1").replace('L', "0");
-    (btoi(rowb), btoi(colb))
-}
+use codec::SeatCodec;
+use seatmap::SeatMap;
 
-fn seat_id((row, col): Seat) -> i32 {
-    row * 8 + col
+fn analyze(map: &SeatMap) {
+    for ((row, col), n) in map.duplicates() {
+        println!("duplicate: row {} col {}: {} passes", row, col, n);
+    }
+    for (row, runs) in map.vacant_runs() {
+        for (col, len) in runs {
+            println!("vacant: row {} cols {}-{}", row, col, col + len - 1);
+        }
+    }
+    print!("{}", map.chart());
 }
 
 fn main() {
-    let path = std::env::args().nth(1).unwrap();
-    let text = std::fs::read_to_string(&path).unwrap();
-    let mut ids: Vec<_> = text.lines().map(parse_seat).map(seat_id).collect();
+    let mut args: Vec<_> = std::env::args().skip(1).collect();
+    let codec = SeatCodec::default();
+    if args[0] == "encode" {
+        let atoi = |s: &String| s.parse::<i32>().unwrap();
+        let seat = (atoi(&args[1]), atoi(&args[2]));
+        println!("{}", codec.encode(seat).unwrap());
+        return;
+    }
+
+    let analyze_map = args[0] == "analyze";
+    if analyze_map {
+        args.remove(0);
+    }
+    let text = std::fs::read_to_string(&args[0]).unwrap();
+    let seats: Result<Vec<_>, _> =
+        text.lines().map(|s| codec.decode(s)).collect();
+    let seats = seats.unwrap();
+    if analyze_map {
+        analyze(&SeatMap::new(codec, &seats));
+        return;
+    }
+
+    let mut ids: Vec<_> =
+        seats.iter().copied().map(|s| codec.seat_id(s)).collect();
     ids.sort();
 
     let max = ids[ids.len() - 1];
@@ -0,0 +1,99 @@
+// seatmap.rs: occupancy analysis over a full plane of boarding passes.
+// Seats before the first and after the last occupied seat id are treated
+// as missing from the plane, not as vacant.
+
+use crate::codec::{Seat, SeatCodec};
+use std::collections::BTreeMap;
+
+pub struct SeatMap {
+    codec: SeatCodec,
+    // pass counts by seat id, so that memory follows the passes rather than
+    // the size of the plane
+    passes: BTreeMap<i32, usize>,
+    first: i32,
+    last: i32,
+}
+
+impl SeatMap {
+    pub fn new(codec: SeatCodec, seats: &[Seat]) -> SeatMap {
+        let mut passes = BTreeMap::new();
+        for &seat in seats {
+            *passes.entry(codec.seat_id(seat)).or_insert(0) += 1;
+        }
+        let ids = passes.keys();
+        let (first, last) = match (ids.clone().next(), ids.last()) {
+            (Some(&first), Some(&last)) => (first, last),
+            _ => (1, 0),
+        };
+        SeatMap {
+            codec,
+            passes,
+            first,
+            last,
+        }
+    }
+
+    fn seat(&self, id: i32) -> Seat {
+        (id / self.codec.cols(), id % self.codec.cols())
+    }
+
+    fn exists(&self, id: i32) -> bool {
+        (self.first..=self.last).contains(&id)
+    }
+
+    fn passes(&self, id: i32) -> usize {
+        self.passes.get(&id).copied().unwrap_or(0)
+    }
+
+    pub fn vacant(&self) -> Vec<Seat> {
+        (self.first..=self.last)
+            .filter(|&id| self.passes(id) == 0)
+            .map(|id| self.seat(id))
+            .collect()
+    }
+
+    // Maps each row to its runs of vacant seats as (first col, length).
+    pub fn vacant_runs(&self) -> BTreeMap<i32, Vec<(i32, i32)>> {
+        let mut runs: BTreeMap<i32, Vec<(i32, i32)>> = BTreeMap::new();
+        for (row, col) in self.vacant() {
+            let row_runs = runs.entry(row).or_default();
+            match row_runs.last_mut() {
+                Some((start, len)) if *start + *len == col => *len += 1,
+                _ => row_runs.push((col, 1)),
+            }
+        }
+        runs
+    }
+
+    pub fn duplicates(&self) -> Vec<(Seat, usize)> {
+        (self.first..=self.last)
+            .filter(|&id| self.passes(id) > 1)
+            .map(|id| (self.seat(id), self.passes(id)))
+            .collect()
+    }
+
+    // Draws one line per row from the first seat's to the last's: '#' is
+    // occupied, '.' is vacant, '!' has duplicate passes, and ' ' is missing
+    // from the plane.
+    pub fn chart(&self) -> String {
+        let mut chart = String::new();
+        if self.first > self.last {
+            return chart;
+        }
+        for row in self.seat(self.first).0..=self.seat(self.last).0 {
+            let mut line = format!("{:4} ", row);
+            for col in 0..self.codec.cols() {
+                let id = self.codec.seat_id((row, col));
+                line.push(match self.passes(id) {
+                    _ if !self.exists(id) => ' ',
+                    0 => '.',
+                    1 => '#',
+                    _ => '!',
+                });
+            }
+            chart += line.trim_end();
+            chart.push('\n');
+        }
+        chart
+    }
+}
@@ -0,0 +1,83 @@
+// answers.rs: a set of questions answered "yes", stored as a bitmask with
+// one bit per question. Questions are the letters a-z and A-Z.
+
+use std::ops::{BitAnd, BitOr, Not};
+
+const QUESTIONS: u32 = 52;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Answers(u64);
+
+fn index(q: char) -> Option<u32> {
+    match q {
+        'a'..='z' => Some(q as u32 - 'a' as u32),
+        'A'..='Z' => Some(q as u32 - 'A' as u32 + 26),
+        _ => None,
+    }
+}
+
+fn question(i: u32) -> char {
+    if i < 26 {
+        (b'a' + i as u8) as char
+    } else {
+        (b'A' + (i - 26) as u8) as char
+    }
+}
+
+impl Answers {
+    pub fn parse(s: &str) -> Result<Answers, String> {
+        s.chars()
+            .try_fold(Answers::default(), |a, q| match index(q) {
+                Some(i) => Ok(Answers(a.0 | 1 << i)),
+                None => Err(format!("bad question: {:?}", q)),
+            })
+    }
+
+    pub fn all() -> Answers {
+        Answers((1 << QUESTIONS) - 1)
+    }
+
+    pub fn count(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn contains(self, q: char) -> bool {
+        index(q).is_some_and(|i| self.0 & 1 << i != 0)
+    }
+
+    pub fn iter(self) -> impl Iterator<Item = char> {
+        (0..QUESTIONS)
+            .filter(move |i| self.0 & 1 << i != 0)
+            .map(question)
+    }
+
+    pub fn filter<F: Fn(char) -> bool>(self, f: F) -> Answers {
+        let bits = (0..QUESTIONS).filter(|&i| self.0 & 1 << i != 0);
+        let bits = bits.filter(|&i| f(question(i)));
+        Answers(bits.fold(0, |a, i| a | 1 << i))
+    }
+}
+
+impl BitOr for Answers {
+    type Output = Answers;
+
+    fn bitor(self, rhs: Answers) -> Answers {
+        Answers(self.0 | rhs.0)
+    }
+}
+
+impl BitAnd for Answers {
+    type Output = Answers;
+
+    fn bitand(self, rhs: Answers) -> Answers {
+        Answers(self.0 & rhs.0)
+    }
+}
+
+impl Not for Answers {
+    type Output = Answers;
+
+    fn not(self) -> Answers {
+        Answers(!self.0) & Answers::all()
+    }
+}
@@ -1,42 +1,48 @@
-type Person = std::collections::HashSet<char>;
-type Group = Vec<Person>;
+mod answers;
+mod query;
+mod stats;
 
-fn read_groups(s: &str) -> Vec<Group> {
-    s.split("\n\n")
-        .map(|g| {
-            g.lines()
-                .map(|p| p.chars().collect::<Person>())
-                .collect::<Group>()
-        })
-        .collect::<Vec<Group>>()
-}
+use answers::Answers;
+use query::Query;
+use stats::Report;
+
+type Group = Vec<Answers>;
 
-fn full_person() -> Person {
-    "abcdefghijklmnopqrstuvwxyz".chars().collect()
+fn read_groups(s: &str) -> Result<Vec<Group>, String> {
+    s.split("\n\n")
+        .map(|g| g.lines().map(Answers::parse).collect())
+        .collect()
 }
 
-fn process_groups<F>(gs: &[Group], acc: Person, f: F) -> usize
-where
-    F: Fn(&Person, &Person) -> Person,
-{
-    gs.iter()
-        .map(|g| g.iter().fold(acc.clone(), |a, p| f(&a, p)))
-        .map(|g| g.len())
-        .sum()
+fn process_groups(gs: &[Group], q: &Query) -> usize {
+    gs.iter().map(|g| q.eval(g).count()).sum()
 }
 
 fn main() {
-    let path = std::env::args().nth(1).unwrap();
-    let text = std::fs::read_to_string(&path).unwrap();
-    let groups = read_groups(&text);
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    if args[0] == "query" {
+        let q = Query::parse(&args[1]).unwrap();
+        let text = std::fs::read_to_string(&args[2]).unwrap();
+        let groups = read_groups(&text).unwrap();
+        println!("{}", process_groups(&groups, &q));
+        return;
+    }
+    if args[0] == "stats" {
+        let text = std::fs::read_to_string(&args[1]).unwrap();
+        let report = Report::new(&read_groups(&text).unwrap());
+        match args.get(2).map(|s| s.as_str()) {
+            Some("json") => println!("{}", report.json()),
+            _ => print!("{}", report.table()),
+        }
+        return;
+    }
+
+    let text = std::fs::read_to_string(&args[0]).unwrap();
+    let groups = read_groups(&text).unwrap();
 
-    let any_yes = process_groups(&groups, Person::new(), |acc, p| {
-        acc.union(p).cloned().collect()
-    });
+    let any_yes = process_groups(&groups, &Query::Any);
     println!("{}", any_yes);
 
-    let all_yes = process_groups(&groups, full_person(), |acc, p| {
-        acc.intersection(p).cloned().collect()
-    });
+    let all_yes = process_groups(&groups, &Query::All);
     println!("{}", all_yes);
 }
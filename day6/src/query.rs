@@ -0,0 +1,103 @@
+// query.rs: set expressions over the members of a group. Every query
+// selects from the questions that somebody in the group answered, so
+// today's part 1 is "any" and part 2 is "all".
+//
+//     expr   := term ('|' term)*
+//     term   := factor ('&' factor)*
+//     factor := '!' factor | '(' expr ')' | atom
+//     atom   := 'any' | 'all' | 'half' | 'exactly' N | 'atleast' N
+
+use crate::answers::Answers;
+use std::iter::Peekable;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Query {
+    Any,
+    All,
+    AtLeastHalf,
+    Exactly(usize),
+    AtLeast(usize),
+    Not(Box<Query>),
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+}
+
+impl Query {
+    pub fn eval(&self, group: &[Answers]) -> Answers {
+        let any = group.iter().fold(Answers::default(), |a, p| a | *p);
+        let count = |q| group.iter().filter(|p| p.contains(q)).count();
+        match self {
+            Query::Any => any,
+            Query::All => group.iter().fold(any, |a, p| a & *p),
+            Query::AtLeastHalf => any.filter(|q| 2 * count(q) >= group.len()),
+            Query::Exactly(k) => any.filter(|q| count(q) == *k),
+            Query::AtLeast(k) => any.filter(|q| count(q) >= *k),
+            Query::Not(q) => any & !q.eval(group),
+            Query::And(a, b) => a.eval(group) & b.eval(group),
+            Query::Or(a, b) => a.eval(group) | b.eval(group),
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Query, String> {
+        let spaced = ["(", ")", "!", "&", "|"]
+            .iter()
+            .fold(s.to_string(), |s, op| s.replace(op, &format!(" {} ", op)));
+        let mut toks = spaced.split_whitespace().peekable();
+        let q = parse_expr(&mut toks)?;
+        match toks.next() {
+            None => Ok(q),
+            Some(tok) => Err(format!("unexpected token: {}", tok)),
+        }
+    }
+}
+
+type Tokens<'a, I> = &'a mut Peekable<I>;
+
+fn parse_expr<'t, I>(toks: Tokens<I>) -> Result<Query, String>
+where
+    I: Iterator<Item = &'t str>,
+{
+    let mut q = parse_term(toks)?;
+    while toks.next_if_eq(&"|").is_some() {
+        q = Query::Or(Box::new(q), Box::new(parse_term(toks)?));
+    }
+    Ok(q)
+}
+
+fn parse_term<'t, I>(toks: Tokens<I>) -> Result<Query, String>
+where
+    I: Iterator<Item = &'t str>,
+{
+    let mut q = parse_factor(toks)?;
+    while toks.next_if_eq(&"&").is_some() {
+        q = Query::And(Box::new(q), Box::new(parse_factor(toks)?));
+    }
+    Ok(q)
+}
+
+fn parse_factor<'t, I>(toks: Tokens<I>) -> Result<Query, String>
+where
+    I: Iterator<Item = &'t str>,
+{
+    let count = |toks: Tokens<I>| match toks.next() {
+        Some(n) => n.parse().map_err(|_| format!("bad count: {}", n)),
+        None => Err("missing count".to_string()),
+    };
+    match toks.next() {
+        Some("!") => Ok(Query::Not(Box::new(parse_factor(toks)?))),
+        Some("(") => {
+            let q = parse_expr(toks)?;
+            match toks.next() {
+                Some(")") => Ok(q),
+                _ => Err("missing )".to_string()),
+            }
+        }
+        Some("any") => Ok(Query::Any),
+        Some("all") => Ok(Query::All),
+        Some("half") => Ok(Query::AtLeastHalf),
+        Some("exactly") => Ok(Query::Exactly(count(toks)?)),
+        Some("atleast") => Ok(Query::AtLeast(count(toks)?)),
+        Some(tok) => Err(format!("unexpected token: {}", tok)),
+        None => Err("unexpected end of query".to_string()),
+    }
+}
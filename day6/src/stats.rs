@@ -0,0 +1,89 @@
+// stats.rs: aggregate statistics over all of the groups in a survey.
+
+use crate::answers::Answers;
+use crate::Group;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Serialize)]
+pub struct Report {
+    groups: usize,
+    people: usize,
+    // question => number of people who answered it
+    frequency: BTreeMap<char, usize>,
+    // indices of the groups whose members all gave the same answers
+    unanimous: Vec<usize>,
+    // group size => number of groups of that size
+    sizes: BTreeMap<usize, usize>,
+    // question => question => number of people who answered both
+    cooccurrence: BTreeMap<char, BTreeMap<char, usize>>,
+}
+
+impl Report {
+    pub fn new(gs: &[Group]) -> Report {
+        let mut r = Report {
+            groups: gs.len(),
+            people: 0,
+            frequency: BTreeMap::new(),
+            unanimous: Vec::new(),
+            sizes: BTreeMap::new(),
+            cooccurrence: BTreeMap::new(),
+        };
+        for (i, g) in gs.iter().enumerate() {
+            r.people += g.len();
+            *r.sizes.entry(g.len()).or_default() += 1;
+            if g.windows(2).all(|w| w[0] == w[1]) {
+                r.unanimous.push(i);
+            }
+            for p in g {
+                r.add_person(*p);
+            }
+        }
+        r
+    }
+
+    fn add_person(&mut self, p: Answers) {
+        for q in p.iter() {
+            *self.frequency.entry(q).or_default() += 1;
+            let row = self.cooccurrence.entry(q).or_default();
+            for q2 in p.iter().filter(|q2| *q2 != q) {
+                *row.entry(q2).or_default() += 1;
+            }
+        }
+    }
+
+    pub fn json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn table(&self) -> String {
+        let mut s =
+            format!("groups: {}\npeople: {}\n", self.groups, self.people);
+        s += &format!("unanimous groups: {}\n", self.unanimous.len());
+
+        s += "\nsize groups\n";
+        for (size, n) in &self.sizes {
+            s += &format!("{:4} {:6}\n", size, n);
+        }
+
+        s += "\nquestion people\n";
+        for (q, n) in &self.frequency {
+            s += &format!("{:>8} {:6}\n", q, n);
+        }
+
+        s += "\n ";
+        for q in self.frequency.keys() {
+            s += &format!(" {:>4}", q);
+        }
+        s += "\n";
+        for q in self.frequency.keys() {
+            s += &q.to_string();
+            for q2 in self.frequency.keys() {
+                let n = self.cooccurrence[q].get(q2).copied().unwrap_or(0);
+                s += &format!(" {:4}", n);
+            }
+            s += "\n";
+        }
+        s
+    }
+}
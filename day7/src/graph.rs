@@ -0,0 +1,204 @@
+// graph.rs: the bag rules as a graph with interned bag ids. Edges point
+// from a bag to the bags it must contain, weighted by the amount, and are
+// stored in both directions.
+
+use crate::Reqs;
+use num_traits::{CheckedAdd, CheckedMul, One, Zero};
+use std::collections::{HashMap, HashSet};
+
+pub type BagId = usize;
+
+#[derive(Debug, Default)]
+pub struct BagGraph {
+    names: Vec<String>,
+    ids: HashMap<String, BagId>,
+    out: Vec<Vec<(BagId, u32)>>,
+    inn: Vec<Vec<(BagId, u32)>>,
+}
+
+impl BagGraph {
+    pub fn new<I>(rules: I) -> BagGraph
+    where
+        I: IntoIterator<Item = (String, Reqs)>,
+    {
+        let mut g = BagGraph::default();
+        for (bag, reqs) in rules {
+            let from = g.intern(&bag);
+            for (req, amt) in reqs {
+                let to = g.intern(&req);
+                g.out[from].push((to, amt));
+                g.inn[to].push((from, amt));
+            }
+        }
+        g
+    }
+
+    fn intern(&mut self, name: &str) -> BagId {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        let id = self.names.len();
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), id);
+        self.out.push(Vec::new());
+        self.inn.push(Vec::new());
+        id
+    }
+
+    pub fn id(&self, name: &str) -> Result<BagId, String> {
+        match self.ids.get(name) {
+            Some(&id) => Ok(id),
+            None => Err(format!("unknown bag: {}", name)),
+        }
+    }
+
+    pub fn name(&self, id: BagId) -> &str {
+        &self.names[id]
+    }
+
+    fn reachable(adj: &[Vec<(BagId, u32)>], id: BagId) -> HashSet<BagId> {
+        let mut seen = HashSet::new();
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            for &(next, _) in &adj[id] {
+                if seen.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+        seen
+    }
+
+    // Bags that can eventually contain the given bag.
+    pub fn ancestors(&self, id: BagId) -> HashSet<BagId> {
+        Self::reachable(&self.inn, id)
+    }
+
+    // Bags that the given bag eventually contains.
+    pub fn descendants(&self, id: BagId) -> HashSet<BagId> {
+        Self::reachable(&self.out, id)
+    }
+
+    // Orders the bags reachable from roots so that every bag comes before
+    // the bags it contains, or reports a cycle.
+    fn topo_from(&self, roots: &[BagId]) -> Result<Vec<BagId>, String> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Mark {
+            New,
+            Open,
+            Done,
+        }
+        let mut marks = vec![Mark::New; self.names.len()];
+        let mut order = Vec::new();
+        for &root in roots {
+            if marks[root] != Mark::New {
+                continue;
+            }
+            // each frame is a bag and the index of its next edge
+            let mut stack = vec![(root, 0)];
+            marks[root] = Mark::Open;
+            while let Some((id, i)) = stack.pop() {
+                let next = match self.out[id].get(i) {
+                    Some(&(next, _)) => next,
+                    None => {
+                        marks[id] = Mark::Done;
+                        order.push(id);
+                        continue;
+                    }
+                };
+                stack.push((id, i + 1));
+                match marks[next] {
+                    Mark::New => {
+                        marks[next] = Mark::Open;
+                        stack.push((next, 0));
+                    }
+                    Mark::Open => {
+                        let start = stack.iter().position(|f| f.0 == next);
+                        let cycle: Vec<_> = stack[start.unwrap()..]
+                            .iter()
+                            .map(|&(id, _)| self.name(id))
+                            .chain(std::iter::once(self.name(next)))
+                            .collect();
+                        return Err(format!("cycle: {}", cycle.join(" -> ")));
+                    }
+                    Mark::Done => {}
+                }
+            }
+        }
+        order.reverse();
+        Ok(order)
+    }
+
+    pub fn topo_order(&self) -> Result<Vec<BagId>, String> {
+        let roots: Vec<_> = (0..self.names.len()).collect();
+        self.topo_from(&roots)
+    }
+
+    // Counts the bags nested inside the given bag, failing if the count
+    // doesn't fit in T.
+    pub fn contained<T>(&self, id: BagId) -> Result<T, String>
+    where
+        T: Clone + Zero + One + CheckedAdd + CheckedMul + From<u32>,
+    {
+        let overflow =
+            || format!("overflow counting bags in {}", self.name(id));
+        let mut totals: HashMap<BagId, T> = HashMap::new();
+        for bag in self.topo_from(&[id])?.into_iter().rev() {
+            let mut total = T::zero();
+            for (req, amt) in &self.out[bag] {
+                let n = totals[req]
+                    .checked_add(&T::one())
+                    .and_then(|n| n.checked_mul(&T::from(*amt)))
+                    .ok_or_else(overflow)?;
+                total = total.checked_add(&n).ok_or_else(overflow)?;
+            }
+            totals.insert(bag, total);
+        }
+        Ok(totals.remove(&id).unwrap())
+    }
+
+    // Lists every path of containment from one bag to another, along with
+    // how many of the inner bag the path accounts for.
+    pub fn paths(
+        &self,
+        from: BagId,
+        to: BagId,
+    ) -> Result<Vec<(Vec<BagId>, u64)>, String> {
+        self.topo_from(&[from])?;
+        let ancestors = self.ancestors(to);
+        let mut paths = Vec::new();
+        let mut stack = vec![(vec![from], 1u64)];
+        while let Some((path, amt)) = stack.pop() {
+            let last = *path.last().unwrap();
+            if last == to && path.len() > 1 {
+                paths.push((path, amt));
+                continue;
+            }
+            for &(next, n) in self.out[last].iter().rev() {
+                if next != to && !ancestors.contains(&next) {
+                    continue;
+                }
+                let mut path = path.clone();
+                path.push(next);
+                let amt = amt.checked_mul(u64::from(n));
+                let amt = amt.ok_or("overflow counting bags")?;
+                stack.push((path, amt));
+            }
+        }
+        Ok(paths)
+    }
+
+    pub fn dot(&self) -> String {
+        let mut s = String::from("digraph bags {\n");
+        for (id, name) in self.names.iter().enumerate() {
+            if self.out[id].is_empty() && self.inn[id].is_empty() {
+                s += &format!("    {:?};\n", name);
+            }
+            for &(req, amt) in &self.out[id] {
+                let req = self.name(req);
+                s += &format!("    {:?} -> {:?} [label={}];\n", name, req, amt);
+            }
+        }
+        s + "}\n"
+    }
+}
@@ -1,15 +1,18 @@
+mod graph;
+
+use graph::{BagGraph, BagId};
+use num_bigint::BigUint;
 use regex::Regex;
 use std::collections::HashMap;
-use std::collections::HashSet;
 
-type Reqs = HashMap<String, i32>;
+type Reqs = HashMap<String, u32>;
 
 struct Parser {
     req_re: Regex,
 }
 
-fn atoi(s: &str) -> i32 {
-    i32::from_str_radix(s, 10).unwrap()
+fn atoi(s: &str) -> u32 {
+    s.parse().unwrap()
 }
 
 impl Parser {
@@ -39,49 +42,74 @@ impl Parser {
     }
 }
 
-fn containing(
-    rules: &HashMap<String, Reqs>,
-    seen: &HashSet<String>,
-    bag: &str,
-) -> HashSet<String> {
-    let mut bags: HashSet<String> = rules
-        .iter()
-        .filter(|(_, reqs)| reqs.contains_key(bag))
-        .map(|(out, _)| out.clone())
-        .filter(|bag| !seen.contains(bag))
-        .collect();
-    let extra: HashSet<String> = bags
-        .iter()
-        .flat_map(|bag| containing(rules, &bags, bag))
-        .collect();
-    bags.extend(extra.iter().cloned());
-    bags
+fn read_graph(path: &str) -> BagGraph {
+    let text = std::fs::read_to_string(path).unwrap();
+    let parser = Parser::new();
+    BagGraph::new(text.lines().map(|s| parser.parse(s)))
+}
+
+fn print_bags(g: &BagGraph, label: &str, bags: &[BagId]) {
+    let mut names: Vec<_> = bags.iter().map(|&id| g.name(id)).collect();
+    names.sort();
+    println!("{} ({}):", label, names.len());
+    for name in names {
+        println!("  {}", name);
+    }
 }
 
-fn contained(
-    rules: &HashMap<String, Reqs>,
-    memo: &mut HashMap<String, i32>,
-    bag: &str,
-) -> i32 {
-    rules[bag].iter().map(|(out, amt)| {
-        let req = match memo.get(out) {
-            Some(&req) => req,
-            None => {
-                let req = contained(rules, memo, &out) + 1;
-                memo.insert(out.clone(), req);
-                req
+// Prints rules for a graph of the given depth where every "red N" and
+// "blue N" bag contains both red and blue bags from the next level.
+fn generate(levels: usize) {
+    for i in 0..levels {
+        for color in &["red", "blue"] {
+            print!("{} {} bags contain ", color, i);
+            if i + 1 == levels {
+                println!("no other bags.");
+            } else {
+                println!("7 red {} bags, 9 blue {} bags.", i + 1, i + 1);
             }
-        };
-        amt * req
-    }).sum()
+        }
+    }
 }
 
 fn main() {
-    let path = std::env::args().nth(1).unwrap();
-    let text = std::fs::read_to_string(&path).unwrap();
-    let parser = Parser::new();
-    let rules: HashMap<String, Reqs> =
-        text.lines().map(|s| parser.parse(s)).collect();
-    println!("{}", containing(&rules, &HashSet::new(), "shiny gold").len());
-    println!("{}", contained(&rules, &mut HashMap::new(), "shiny gold"));
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    match args[0].as_str() {
+        "topo" => {
+            let g = read_graph(&args[1]);
+            for id in g.topo_order().unwrap() {
+                println!("{}", g.name(id));
+            }
+        }
+        "info" => {
+            let g = read_graph(&args[2]);
+            let id = g.id(&args[1]).unwrap();
+            let ancestors: Vec<_> = g.ancestors(id).into_iter().collect();
+            print_bags(&g, "contained by", &ancestors);
+            let descendants: Vec<_> = g.descendants(id).into_iter().collect();
+            print_bags(&g, "contains", &descendants);
+        }
+        "dot" => print!("{}", read_graph(&args[1]).dot()),
+        "explain" => {
+            let g = read_graph(&args[3]);
+            let (from, to) = (g.id(&args[1]).unwrap(), g.id(&args[2]).unwrap());
+            for (path, amt) in g.paths(from, to).unwrap() {
+                let names: Vec<_> = path.iter().map(|&id| g.name(id)).collect();
+                println!("{}: {}", names.join(" -> "), amt);
+            }
+        }
+        "generate" => generate(args[1].parse().unwrap()),
+        "big" => {
+            let g = read_graph(&args[2]);
+            let id = g.id(&args[1]).unwrap();
+            println!("{}", g.contained::<BigUint>(id).unwrap());
+        }
+        path => {
+            let g = read_graph(path);
+            let bag = args.get(1).map_or("shiny gold", |s| s.as_str());
+            let id = g.id(bag).unwrap();
+            println!("{}", g.ancestors(id).len());
+            println!("{}", g.contained::<u64>(id).unwrap());
+        }
+    }
 }
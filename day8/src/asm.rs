@@ -0,0 +1,305 @@
+// asm.rs: assembler, disassembler and binary encoding for programs.
+//
+// Source files have one instruction per line. Anything after a '#' is a
+// comment, and a line may start with one or more "name:" labels. Jump
+// offsets can be signed numbers or labels, which the assembler resolves
+// to the offset of the labelled instruction:
+//
+//     loop: acc +1   # count forever
+//           jmp loop
+//
+// Besides acc, jmp and nop, there are registers r1 to r7:
+//
+//     mov r1 +5      # r1 = 5
+//     add r1 r2      # r1 += r2
+//     mul acc r1     # acc *= r1
+//     jz r1 done     # jump if r1 == 0
+//     jnz r1 -3      # jump if r1 != 0
+//     out acc        # print acc
+//     halt
+
+use crate::{Instr, Reg, Val, REGS};
+use std::collections::HashMap;
+
+const MAGIC: &[u8; 4] = b"HHVM";
+
+pub fn reg_name(r: Reg) -> String {
+    match r {
+        0 => "acc".to_string(),
+        r => format!("r{}", r),
+    }
+}
+
+struct Line<'t> {
+    num: usize,
+    op: &'t str,
+    args: Vec<&'t str>,
+}
+
+fn is_label(s: &str) -> bool {
+    let word = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    !s.is_empty()
+        && !s.starts_with(|c: char| c.is_ascii_digit())
+        && s.chars().all(word)
+}
+
+// Splits the source into instruction lines, mapping each label to the
+// index of the instruction that follows it.
+fn scan(s: &str) -> Result<(Vec<Line<'_>>, HashMap<&str, usize>), String> {
+    let mut lines = Vec::new();
+    let mut labels = HashMap::new();
+    for (i, line) in s.lines().enumerate() {
+        let err = |e| format!("line {}: {}", i + 1, e);
+        let mut rest = line.split('#').next().unwrap().trim();
+        while let Some((label, tail)) = rest.split_once(':') {
+            let label = label.trim();
+            if !is_label(label) {
+                return Err(err(format!("bad label: {:?}", label)));
+            }
+            if labels.insert(label, lines.len()).is_some() {
+                return Err(err(format!("duplicate label: {}", label)));
+            }
+            rest = tail.trim();
+        }
+        let mut toks = rest.split_whitespace();
+        if let Some(op) = toks.next() {
+            let args = toks.collect();
+            lines.push(Line {
+                num: i + 1,
+                op,
+                args,
+            });
+        }
+    }
+    Ok((lines, labels))
+}
+
+struct Operands<'a, 't> {
+    pc: usize,
+    args: &'a [&'t str],
+    labels: &'a HashMap<&'t str, usize>,
+}
+
+impl<'a, 't> Operands<'a, 't> {
+    fn arg(&self, i: usize) -> Result<&'t str, String> {
+        match self.args.get(i) {
+            Some(arg) => Ok(arg),
+            None => Err(format!("missing operand {}", i + 1)),
+        }
+    }
+
+    fn imm(&self, i: usize) -> Result<i32, String> {
+        let arg = self.arg(i)?;
+        arg.parse().map_err(|_| format!("bad number: {}", arg))
+    }
+
+    fn reg(&self, i: usize) -> Result<Reg, String> {
+        let arg = self.arg(i)?;
+        (0..REGS)
+            .find(|&r| reg_name(r) == arg)
+            .ok_or_else(|| format!("bad register: {}", arg))
+    }
+
+    fn val(&self, i: usize) -> Result<Val, String> {
+        match self.reg(i) {
+            Ok(r) => Ok(Val::Reg(r)),
+            Err(_) => self.imm(i).map(Val::Imm),
+        }
+    }
+
+    fn offset(&self, i: usize) -> Result<i32, String> {
+        match self.labels.get(self.arg(i)?) {
+            Some(&target) => Ok(target as i32 - self.pc as i32),
+            None => self.imm(i),
+        }
+    }
+}
+
+fn parse(line: &Line, ops: &Operands) -> Result<Instr, String> {
+    let (instr, arity) = match line.op {
+        "acc" => (Instr::Acc(ops.imm(0)?), 1),
+        "jmp" => (Instr::Jmp(ops.offset(0)?), 1),
+        "nop" => (Instr::Nop(ops.offset(0)?), 1),
+        "mov" => (Instr::Mov(ops.reg(0)?, ops.val(1)?), 2),
+        "add" => (Instr::Add(ops.reg(0)?, ops.val(1)?), 2),
+        "mul" => (Instr::Mul(ops.reg(0)?, ops.val(1)?), 2),
+        "jz" => (Instr::Jz(ops.val(0)?, ops.offset(1)?), 2),
+        "jnz" => (Instr::Jnz(ops.val(0)?, ops.offset(1)?), 2),
+        "out" => (Instr::Out(ops.val(0)?), 1),
+        "halt" => (Instr::Halt, 0),
+        op => return Err(format!("bad instruction: {}", op)),
+    };
+    if line.args.len() != arity {
+        return Err(format!("{} takes {} operands", line.op, arity));
+    }
+    Ok(instr)
+}
+
+pub fn assemble(s: &str) -> Result<Vec<Instr>, String> {
+    let (lines, labels) = scan(s)?;
+    let mut prog = Vec::new();
+    for (pc, line) in lines.iter().enumerate() {
+        let ops = Operands {
+            pc,
+            args: &line.args,
+            labels: &labels,
+        };
+        let instr = parse(line, &ops);
+        prog.push(instr.map_err(|e| format!("line {}: {}", line.num, e))?);
+    }
+    Ok(prog)
+}
+
+fn num_source(n: i32) -> String {
+    format!("{:+}", n)
+}
+
+fn val_source(v: Val) -> String {
+    match v {
+        Val::Reg(r) => reg_name(r),
+        Val::Imm(n) => num_source(n),
+    }
+}
+
+pub fn disassemble(prog: &[Instr]) -> String {
+    let mut s = String::new();
+    for instr in prog {
+        let (op, args) = match *instr {
+            Instr::Acc(n) => ("acc", vec![num_source(n)]),
+            Instr::Jmp(n) => ("jmp", vec![num_source(n)]),
+            Instr::Nop(n) => ("nop", vec![num_source(n)]),
+            Instr::Mov(r, v) => ("mov", vec![reg_name(r), val_source(v)]),
+            Instr::Add(r, v) => ("add", vec![reg_name(r), val_source(v)]),
+            Instr::Mul(r, v) => ("mul", vec![reg_name(r), val_source(v)]),
+            Instr::Jz(v, n) => ("jz", vec![val_source(v), num_source(n)]),
+            Instr::Jnz(v, n) => ("jnz", vec![val_source(v), num_source(n)]),
+            Instr::Out(v) => ("out", vec![val_source(v)]),
+            Instr::Halt => ("halt", vec![]),
+        };
+        s += op;
+        for arg in args {
+            s += " ";
+            s += &arg;
+        }
+        s += "\n";
+    }
+    s
+}
+
+// Programs are encoded as MAGIC and the instruction count, followed by
+// an opcode byte and the operands of each instruction. Numbers are four
+// bytes little-endian, registers are one byte, and values are a tag byte
+// (0 for a number, 1 for a register) followed by either.
+fn encode_val(bytes: &mut Vec<u8>, v: Val) {
+    match v {
+        Val::Imm(n) => {
+            bytes.push(0);
+            bytes.extend(&n.to_le_bytes());
+        }
+        Val::Reg(r) => bytes.extend(&[1, r as u8]),
+    }
+}
+
+pub fn encode(prog: &[Instr]) -> Vec<u8> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend(&(prog.len() as u32).to_le_bytes());
+    for instr in prog {
+        let (op, reg, val, num) = match *instr {
+            Instr::Acc(n) => (0, None, None, Some(n)),
+            Instr::Jmp(n) => (1, None, None, Some(n)),
+            Instr::Nop(n) => (2, None, None, Some(n)),
+            Instr::Mov(r, v) => (3, Some(r), Some(v), None),
+            Instr::Add(r, v) => (4, Some(r), Some(v), None),
+            Instr::Mul(r, v) => (5, Some(r), Some(v), None),
+            Instr::Jz(v, n) => (6, None, Some(v), Some(n)),
+            Instr::Jnz(v, n) => (7, None, Some(v), Some(n)),
+            Instr::Out(v) => (8, None, Some(v), None),
+            Instr::Halt => (9, None, None, None),
+        };
+        bytes.push(op);
+        if let Some(r) = reg {
+            bytes.push(r as u8);
+        }
+        if let Some(v) = val {
+            encode_val(&mut bytes, v);
+        }
+        if let Some(n) = num {
+            bytes.extend(&n.to_le_bytes());
+        }
+    }
+    bytes
+}
+
+pub fn is_encoded(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+struct Decoder<'b> {
+    bytes: &'b [u8],
+    at: usize,
+}
+
+impl<'b> Decoder<'b> {
+    fn take(&mut self, n: usize) -> Result<&'b [u8], String> {
+        let b = self.bytes.get(self.at..self.at + n);
+        let b = b.ok_or_else(|| format!("truncated at byte {}", self.at))?;
+        self.at += n;
+        Ok(b)
+    }
+
+    fn err<T>(&self, what: &str, b: u8) -> Result<T, String> {
+        Err(format!("bad {} at byte {}: {}", what, self.at - 1, b))
+    }
+
+    fn num(&mut self) -> Result<i32, String> {
+        let b = self.take(4)?;
+        Ok(i32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn reg(&mut self) -> Result<Reg, String> {
+        match self.take(1)?[0] {
+            r if (r as Reg) < REGS => Ok(r as Reg),
+            r => self.err("register", r),
+        }
+    }
+
+    fn val(&mut self) -> Result<Val, String> {
+        match self.take(1)?[0] {
+            0 => Ok(Val::Imm(self.num()?)),
+            1 => Ok(Val::Reg(self.reg()?)),
+            tag => self.err("value tag", tag),
+        }
+    }
+
+    fn instr(&mut self) -> Result<Instr, String> {
+        Ok(match self.take(1)?[0] {
+            0 => Instr::Acc(self.num()?),
+            1 => Instr::Jmp(self.num()?),
+            2 => Instr::Nop(self.num()?),
+            3 => Instr::Mov(self.reg()?, self.val()?),
+            4 => Instr::Add(self.reg()?, self.val()?),
+            5 => Instr::Mul(self.reg()?, self.val()?),
+            6 => Instr::Jz(self.val()?, self.num()?),
+            7 => Instr::Jnz(self.val()?, self.num()?),
+            8 => Instr::Out(self.val()?),
+            9 => Instr::Halt,
+            op => return self.err("opcode", op),
+        })
+    }
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Vec<Instr>, String> {
+    if !is_encoded(bytes) {
+        return Err("missing magic number".to_string());
+    }
+    let mut d = Decoder { bytes, at: 4 };
+    let len = d.num()? as u32;
+    let mut prog = Vec::new();
+    for _ in 0..len {
+        prog.push(d.instr()?);
+    }
+    if d.at != bytes.len() {
+        return Err("trailing bytes after program".to_string());
+    }
+    Ok(prog)
+}
@@ -0,0 +1,95 @@
+// cfg.rs: control-flow analysis. The instructions that can terminate are
+// found by walking predecessor edges back from prog.len() in linear time;
+// halt counts as an edge to prog.len(). Conditional jumps on a register
+// have two successors, so for programs that use them "can terminate"
+// means "terminates for some register values".
+
+use crate::{Instr, Seen, Val, VM};
+
+fn successors(instr: Instr, pc: usize, len: usize) -> Vec<usize> {
+    let offsets = match instr {
+        Instr::Jmp(n) => vec![n],
+        Instr::Jz(Val::Imm(v), n) => vec![if v == 0 { n } else { 1 }],
+        Instr::Jnz(Val::Imm(v), n) => vec![if v != 0 { n } else { 1 }],
+        Instr::Jz(_, n) | Instr::Jnz(_, n) => vec![1, n],
+        Instr::Halt => return vec![len],
+        _ => vec![1],
+    };
+    offsets
+        .into_iter()
+        .map(|n| pc as i64 + n as i64)
+        .filter(|&next| next >= 0 && next as usize <= len)
+        .map(|next| next as usize)
+        .collect()
+}
+
+pub fn flip(instr: Instr) -> Option<Instr> {
+    match instr {
+        Instr::Jmp(n) => Some(Instr::Nop(n)),
+        Instr::Nop(n) => Some(Instr::Jmp(n)),
+        _ => None,
+    }
+}
+
+// Marks the pcs from which the program can run off its end or halt. The
+// result has an extra entry for prog.len() itself.
+pub fn terminating(prog: &[Instr]) -> Vec<bool> {
+    let mut preds = vec![Vec::new(); prog.len() + 1];
+    for (pc, &instr) in prog.iter().enumerate() {
+        for next in successors(instr, pc, prog.len()) {
+            preds[next].push(pc);
+        }
+    }
+    let mut terminates = vec![false; prog.len() + 1];
+    terminates[prog.len()] = true;
+    let mut stack = vec![prog.len()];
+    while let Some(pc) = stack.pop() {
+        for &pred in &preds[pc] {
+            if !terminates[pred] {
+                terminates[pred] = true;
+                stack.push(pred);
+            }
+        }
+    }
+    terminates
+}
+
+// The pcs that the program runs before it loops or stops, each once, and
+// whether it terminated.
+fn path(prog: &[Instr]) -> (Vec<usize>, bool) {
+    let mut vm = VM::new(prog);
+    let mut seen = Seen::new(prog);
+    let mut on_path = vec![false; prog.len()];
+    let mut path = Vec::new();
+    while vm.status().is_none() && seen.insert(&vm) {
+        let pc = vm.pc as usize;
+        if !on_path[pc] {
+            on_path[pc] = true;
+            path.push(pc);
+        }
+        vm.step();
+    }
+    (
+        path,
+        vm.status().is_some_and(|outcome| outcome.terminated()),
+    )
+}
+
+// Finds every jmp or nop whose flip makes the program terminate. Only
+// instructions on the original path can matter, and a flip there works
+// iff the flipped successor could already terminate. Returns no
+// candidates if the program terminates as is.
+pub fn repairs(prog: &[Instr]) -> Vec<usize> {
+    let (path, terminated) = path(prog);
+    if terminated {
+        return Vec::new();
+    }
+    let terminates = terminating(prog);
+    path.into_iter()
+        .filter(|&pc| {
+            let next =
+                flip(prog[pc]).map(|instr| successors(instr, pc, prog.len()));
+            next.is_some_and(|next| next.iter().any(|&pc| terminates[pc]))
+        })
+        .collect()
+}
@@ -0,0 +1,200 @@
+// debugger.rs: runs a VM with breakpoints, a watchpoint on acc, and a
+// recorded history of VM states that can be stepped back through.
+
+use crate::{asm, Guard, Instr, Outcome, Policy, Snapshot, VM};
+use std::collections::BTreeSet;
+use std::io::BufRead;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    Stepped,
+    Breakpoint(i32),
+    Watchpoint { pc: i32, old: i32, new: i32 },
+    Finished(Outcome),
+}
+
+pub struct Debugger<'t> {
+    vm: VM<'t>,
+    breakpoints: BTreeSet<i32>,
+    watch_acc: bool,
+    history: Vec<Snapshot>,
+    visits: Vec<usize>,
+}
+
+impl<'t> Debugger<'t> {
+    pub fn new(prog: &'t [Instr]) -> Self {
+        Debugger {
+            vm: VM::new(prog),
+            breakpoints: BTreeSet::new(),
+            watch_acc: false,
+            history: Vec::new(),
+            visits: vec![0; prog.len()],
+        }
+    }
+
+    pub fn set_breakpoint(&mut self, pc: i32) {
+        self.breakpoints.insert(pc);
+    }
+
+    pub fn clear_breakpoint(&mut self, pc: i32) -> bool {
+        self.breakpoints.remove(&pc)
+    }
+
+    pub fn watch_acc(&mut self, watch: bool) {
+        self.watch_acc = watch;
+    }
+
+    // How many times each instruction has been executed.
+    pub fn visits(&self) -> &[usize] {
+        &self.visits
+    }
+
+    pub fn cur(&self) -> Option<&Instr> {
+        self.vm.prog.get(self.vm.pc as usize)
+    }
+
+    pub fn step(&mut self) -> Stop {
+        if let Some(outcome) = self.vm.status() {
+            return Stop::Finished(outcome);
+        }
+        let (pc, acc) = (self.vm.pc, self.vm.acc());
+        self.history.push(self.vm.snapshot());
+        self.visits[pc as usize] += 1;
+        self.vm.step();
+        if let Some(outcome) = self.vm.status() {
+            return Stop::Finished(outcome);
+        }
+        if self.watch_acc && self.vm.acc() != acc {
+            let new = self.vm.acc();
+            return Stop::Watchpoint { pc, old: acc, new };
+        }
+        Stop::Stepped
+    }
+
+    pub fn step_back(&mut self) -> bool {
+        match self.history.pop() {
+            Some(snapshot) => {
+                self.visits[snapshot.pc as usize] -= 1;
+                self.vm.restore(snapshot);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Runs until a breakpoint or watchpoint triggers, the program ends, or
+    // the policy stops it. As in VM::run, loops are detected by a state
+    // repeating within this call, so continuing from a reported loop goes
+    // around it once more.
+    pub fn cont(&mut self, policy: Policy) -> Stop {
+        let mut guard = Guard::new(self.vm.prog, policy);
+        let mut started = false;
+        loop {
+            let pc = self.vm.pc;
+            if started && self.breakpoints.contains(&pc) {
+                return Stop::Breakpoint(pc);
+            }
+            if let Some(outcome) = guard.check(&self.vm) {
+                return Stop::Finished(outcome);
+            }
+            started = true;
+            match self.step() {
+                Stop::Stepped => {}
+                stop => return stop,
+            }
+        }
+    }
+
+    pub fn state(&self) -> String {
+        let instr = match self.cur() {
+            Some(instr) => asm::disassemble(&[*instr]).trim().to_string(),
+            None => "<end>".to_string(),
+        };
+        let mut s = format!("pc={}", self.vm.pc);
+        for (r, &n) in self.vm.regs.iter().enumerate() {
+            if r == 0 || n != 0 {
+                s += &format!(" {}={}", asm::reg_name(r), n);
+            }
+        }
+        if let Some(n) = self.vm.out.last() {
+            s += &format!(" out={}", n);
+        }
+        s + " " + &instr
+    }
+}
+
+const HELP: &str = "commands:
+  s [n]       step n instructions
+  r [n]       step back n instructions
+  c [policy]  continue, stopping at loops (the default), at the end
+              (complete), or after n steps (budget n)
+  b <pc>      set a breakpoint
+  d <pc>      delete a breakpoint
+  w           toggle the watchpoint on acc
+  v           show instruction visit counts
+  p           show the current state
+  q           quit";
+
+// Reads debugger commands from stdin until EOF or "q".
+pub fn repl(prog: &[Instr]) {
+    let mut dbg = Debugger::new(prog);
+    let mut watch = false;
+    println!("{}", dbg.state());
+    for line in std::io::stdin().lock().lines() {
+        let line = line.unwrap();
+        let mut toks = line.split_whitespace();
+        let cmd = match toks.next() {
+            Some(cmd) => cmd,
+            None => continue,
+        };
+        let rest: Vec<_> = toks.map(|s| s.to_string()).collect();
+        let arg: Option<i32> = rest.first().and_then(|s| s.parse().ok());
+        match (cmd, arg) {
+            ("s", n) => {
+                for _ in 0..n.unwrap_or(1) {
+                    match dbg.step() {
+                        Stop::Stepped => {}
+                        stop => {
+                            println!("{:?}", stop);
+                            break;
+                        }
+                    }
+                }
+            }
+            ("r", n) => {
+                for _ in 0..n.unwrap_or(1) {
+                    if !dbg.step_back() {
+                        println!("at start of history");
+                        break;
+                    }
+                }
+            }
+            ("c", _) => match Policy::parse(&rest) {
+                Ok(policy) => println!("{:?}", dbg.cont(policy)),
+                Err(e) => println!("{}", e),
+            },
+            ("b", Some(pc)) => dbg.set_breakpoint(pc),
+            ("d", Some(pc)) => {
+                if !dbg.clear_breakpoint(pc) {
+                    println!("no breakpoint at {}", pc);
+                }
+            }
+            ("w", _) => {
+                watch = !watch;
+                dbg.watch_acc(watch);
+                println!("watching acc: {}", watch);
+            }
+            ("v", _) => {
+                for (pc, n) in dbg.visits().iter().enumerate() {
+                    if *n > 0 {
+                        println!("{:5} {}", pc, n);
+                    }
+                }
+            }
+            ("p", _) => {}
+            ("q", _) => return,
+            _ => println!("{}", HELP),
+        }
+        println!("{}", dbg.state());
+    }
+}
@@ -1,95 +1,332 @@
-fn atoi(s: &str) -> i32 {
-    i32::from_str_radix(s, 10).unwrap()
+mod asm;
+mod cfg;
+mod debugger;
+
+use std::collections::HashSet;
+
+// Registers are indexed from 0, and register 0 is acc.
+type Reg = usize;
+const REGS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Val {
+    Reg(Reg),
+    Imm(i32),
 }
 
+// Jump offsets are relative to the jumping instruction.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Instr {
     Acc(i32),
     Jmp(i32),
     Nop(i32),
+    Mov(Reg, Val),
+    Add(Reg, Val),
+    Mul(Reg, Val),
+    Jz(Val, i32),
+    Jnz(Val, i32),
+    Out(Val),
+    Halt,
+}
+
+// Reads a program in either source or binary form.
+fn read_program(path: &str) -> Result<Vec<Instr>, String> {
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    if asm::is_encoded(&bytes) {
+        return asm::decode(&bytes);
+    }
+    let text = String::from_utf8(bytes).map_err(|e| e.to_string())?;
+    asm::assemble(&text)
 }
 
-impl Instr {
-    fn parse(s: &str) -> Self {
-        let mut toks = s.split(' ');
-        let op = toks.next().unwrap();
-        let arg = atoi(toks.next().unwrap());
-        match op {
-            "acc" => Instr::Acc(arg),
-            "jmp" => Instr::Jmp(arg),
-            "nop" => Instr::Nop(arg),
-            _ => unreachable!(),
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    // ran off the end of the program
+    Terminated,
+    Halted(i32),
+    // about to run the instruction at pc in a state it has been in before
+    Looped(i32),
+    JumpedOut(i32),
+    BudgetExceeded,
+    Overflow(i32),
+}
+
+impl Outcome {
+    fn terminated(self) -> bool {
+        matches!(self, Outcome::Terminated | Outcome::Halted(_))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Policy {
+    DetectLoops,
+    Budget(usize),
+    Complete,
+}
+
+impl Policy {
+    fn parse(args: &[String]) -> Result<Policy, String> {
+        match args.first().map(|s| s.as_str()) {
+            None | Some("loops") => Ok(Policy::DetectLoops),
+            Some("complete") => Ok(Policy::Complete),
+            Some("budget") => match args.get(1).map(|n| n.parse()) {
+                Some(Ok(n)) => Ok(Policy::Budget(n)),
+                _ => Err("budget needs a step count".to_string()),
+            },
+            Some(s) => Err(format!("bad policy: {}", s)),
         }
     }
 }
 
-fn read_program(s: &str) -> Vec<Instr> {
-    s.lines().map(Instr::parse).collect()
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Snapshot {
+    regs: [i32; REGS],
+    pc: i32,
+    out_len: usize,
+    stopped: Option<Outcome>,
 }
 
 struct VM<'t> {
-    acc: i32,
+    regs: [i32; REGS],
     pc: i32,
     prog: &'t [Instr],
+    out: Vec<i32>,
+    stopped: Option<Outcome>,
 }
 
 impl<'t> VM<'t> {
     fn new(prog: &'t [Instr]) -> Self {
-        VM { acc: 0, pc: 0, prog }
+        VM {
+            regs: [0; REGS],
+            pc: 0,
+            prog,
+            out: Vec::new(),
+            stopped: None,
+        }
     }
 
-    fn cur(&self) -> &Instr {
-        &self.prog[self.pc as usize]
+    fn acc(&self) -> i32 {
+        self.regs[0]
     }
 
-    fn step(&mut self) {
-        match *self.cur() {
-            Instr::Acc(n) => {
-                self.acc += n;
-                self.pc += 1;
+    fn get(&self, v: Val) -> i32 {
+        match v {
+            Val::Reg(r) => self.regs[r],
+            Val::Imm(n) => n,
+        }
+    }
+
+    // Returns why the VM can't step any further, if it can't.
+    fn status(&self) -> Option<Outcome> {
+        let len = self.prog.len() as i32;
+        match self.stopped {
+            Some(outcome) => Some(outcome),
+            None if self.pc == len => Some(Outcome::Terminated),
+            None if self.pc < 0 || self.pc > len => {
+                Some(Outcome::JumpedOut(self.pc))
             }
-            Instr::Jmp(n) => self.pc += n,
-            Instr::Nop(_) => self.pc += 1,
+            None => None,
         }
     }
 
-    fn done(&self) -> bool {
-        self.pc == (self.prog.len() as i32)
+    fn set(&mut self, r: Reg, n: Option<i32>) {
+        match n {
+            Some(n) => self.regs[r] = n,
+            None => self.stopped = Some(Outcome::Overflow(self.pc)),
+        }
+    }
+
+    fn step(&mut self) {
+        if self.status().is_some() {
+            return;
+        }
+        let mut offset = 1;
+        match self.prog[self.pc as usize] {
+            Instr::Acc(n) => self.set(0, self.regs[0].checked_add(n)),
+            Instr::Jmp(n) => offset = n,
+            Instr::Nop(_) => {}
+            Instr::Mov(r, v) => self.regs[r] = self.get(v),
+            Instr::Add(r, v) => {
+                self.set(r, self.regs[r].checked_add(self.get(v)))
+            }
+            Instr::Mul(r, v) => {
+                self.set(r, self.regs[r].checked_mul(self.get(v)))
+            }
+            Instr::Jz(v, n) if self.get(v) == 0 => offset = n,
+            Instr::Jnz(v, n) if self.get(v) != 0 => offset = n,
+            Instr::Jz(_, _) | Instr::Jnz(_, _) => {}
+            Instr::Out(v) => self.out.push(self.get(v)),
+            Instr::Halt => self.stopped = Some(Outcome::Halted(self.pc)),
+        }
+        if self.stopped.is_none() {
+            self.pc = self.pc.saturating_add(offset);
+        }
     }
 
-    fn run_until_loop(&mut self) {
-        let mut seen = vec![false; self.prog.len()];
-        while !self.done() && !seen[self.pc as usize] {
-            seen[self.pc as usize] = true;
+    fn run(&mut self, policy: Policy) -> Outcome {
+        let mut guard = Guard::new(self.prog, policy);
+        loop {
+            if let Some(outcome) = guard.check(self) {
+                return outcome;
+            }
             self.step();
         }
     }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            regs: self.regs,
+            pc: self.pc,
+            out_len: self.out.len(),
+            stopped: self.stopped,
+        }
+    }
+
+    fn restore(&mut self, s: Snapshot) {
+        self.regs = s.regs;
+        self.pc = s.pc;
+        self.out.truncate(s.out_len);
+        self.stopped = s.stopped;
+    }
 }
 
-fn main() {
-    let path = std::env::args().nth(1).unwrap();
-    let text = std::fs::read_to_string(&path).unwrap();
-    let prog = read_program(&text);
+// The states a VM has been in before its next step, for finding loops.
+// Control flow only depends on the registers through jz and jnz on a
+// register, so for programs without those the pc alone is enough.
+struct Seen {
+    pcs: Vec<bool>,
+    states: Option<HashSet<(i32, [i32; REGS])>>,
+}
+
+impl Seen {
+    fn new(prog: &[Instr]) -> Self {
+        let reads_regs = prog.iter().any(|instr| {
+            matches!(
+                instr,
+                Instr::Jz(Val::Reg(_), _) | Instr::Jnz(Val::Reg(_), _)
+            )
+        });
+        Seen {
+            pcs: vec![false; prog.len()],
+            states: Some(HashSet::new()).filter(|_| reads_regs),
+        }
+    }
+
+    // Records the state of a VM that can still step, returning false if it
+    // was already recorded.
+    fn insert(&mut self, vm: &VM) -> bool {
+        match &mut self.states {
+            Some(states) => states.insert((vm.pc, vm.regs)),
+            None => !std::mem::replace(&mut self.pcs[vm.pc as usize], true),
+        }
+    }
+}
+
+// Applies a policy to a run, one step at a time.
+struct Guard {
+    policy: Policy,
+    seen: Seen,
+    steps: usize,
+}
 
+impl Guard {
+    fn new(prog: &[Instr], policy: Policy) -> Self {
+        Guard {
+            policy,
+            seen: Seen::new(prog),
+            steps: 0,
+        }
+    }
+
+    // Why the VM has to stop before its next step, if it does. Otherwise
+    // counts the step.
+    fn check(&mut self, vm: &VM) -> Option<Outcome> {
+        if let Some(outcome) = vm.status() {
+            return Some(outcome);
+        }
+        match self.policy {
+            Policy::DetectLoops if !self.seen.insert(vm) => {
+                return Some(Outcome::Looped(vm.pc));
+            }
+            Policy::Budget(n) if self.steps == n => {
+                return Some(Outcome::BudgetExceeded);
+            }
+            _ => {}
+        }
+        self.steps += 1;
+        None
+    }
+}
+
+// Runs the program with the instruction at pc flipped between jmp and nop.
+fn run_flipped(prog: &[Instr], pc: usize) -> (Outcome, i32) {
+    let mut prog = prog.to_vec();
+    prog[pc] = cfg::flip(prog[pc]).unwrap();
     let mut vm = VM::new(&prog);
-    vm.run_until_loop();
-    println!("{}", vm.acc);
-
-    let mut prog = prog;
-    for i in 0..prog.len() {
-        let prev = prog[i];
-        prog[i] = match prev {
-            Instr::Acc(_) => prev,
-            Instr::Jmp(n) => Instr::Nop(n),
-            Instr::Nop(n) => Instr::Jmp(n),
-        };
-        let mut vm = VM::new(&prog);
-        vm.run_until_loop();
-        if vm.done() {
-            println!("{}", vm.acc);
+    (vm.run(Policy::DetectLoops), vm.acc())
+}
+
+fn main() {
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    match args[0].as_str() {
+        "asm" => {
+            let prog = read_program(&args[1]).unwrap();
+            std::fs::write(&args[2], asm::encode(&prog)).unwrap();
+            return;
+        }
+        "debug" => {
+            debugger::repl(&read_program(&args[1]).unwrap());
+            return;
+        }
+        "cfg" => {
+            let prog = read_program(&args[1]).unwrap();
+            let terminating = cfg::terminating(&prog);
+            let n = terminating.iter().filter(|&&t| t).count() - 1;
+            println!("{} of {} instructions terminate", n, prog.len());
+            for pc in cfg::repairs(&prog) {
+                let instr = asm::disassemble(&prog[pc..=pc]);
+                let (outcome, acc) = run_flipped(&prog, pc);
+                println!(
+                    "flip {}: {} ({:?}, acc {})",
+                    pc,
+                    instr.trim(),
+                    outcome,
+                    acc
+                );
+            }
             return;
         }
-        prog[i] = prev;
+        "disasm" => {
+            print!("{}", asm::disassemble(&read_program(&args[1]).unwrap()));
+            return;
+        }
+        "run" => {
+            let prog = read_program(&args[1]).unwrap();
+            let policy = Policy::parse(&args[2..]).unwrap();
+            let mut vm = VM::new(&prog);
+            println!("{:?}", vm.run(policy));
+            for (r, n) in vm.regs.iter().enumerate() {
+                println!("{} = {}", asm::reg_name(r), n);
+            }
+            for n in &vm.out {
+                println!("out: {}", n);
+            }
+            return;
+        }
+        _ => {}
     }
-    unreachable!();
+    let prog = read_program(&args[0]).unwrap();
+
+    let mut vm = VM::new(&prog);
+    vm.run(Policy::DetectLoops);
+    println!("{}", vm.acc());
+
+    // with conditional jumps the analysis can only say which flips might
+    // terminate, so run each candidate to check
+    let acc = cfg::repairs(&prog)
+        .into_iter()
+        .map(|pc| run_flipped(&prog, pc))
+        .find(|(outcome, _)| outcome.terminated())
+        .map(|(_, acc)| acc);
+    println!("{}", acc.expect("no single flip terminates the program"));
 }
@@ -1,101 +1,198 @@
 use std::collections::HashMap;
 use std::collections::VecDeque;
+use std::io::BufRead;
 
 fn atoi(s: &str) -> i64 {
-    i64::from_str_radix(s, 10).unwrap()
+    s.parse().unwrap()
 }
 
-const WINDOW_SIZE: usize = 25;
-
-type Timestamp = usize;
+const DEFAULT_WINDOW: usize = 25;
 
+// Checks each number against the sums of pairs of distinct numbers among
+// the previous `size` numbers. The first `size` numbers are the preamble
+// and are always valid.
 #[derive(Debug)]
 struct WindowedValidator {
     size: usize,
-    sums: HashMap<i64, Timestamp>,
-    cache: VecDeque<(i64, Timestamp)>,
+    window: VecDeque<i64>,
+    counts: HashMap<i64, usize>,
 }
 
 impl WindowedValidator {
-    fn new<I>(preamble: I) -> Self where I: Iterator<Item = i64> {
-        let cache: VecDeque<_> =
-            preamble.enumerate().map(|(i, x)| (x, i)).collect();
-        let mut validator = Self {
-            size: cache.len(),
-            sums: HashMap::new(),
-            cache: cache.clone(),
-        };
-        cache.iter().for_each(|&(x, xts)| validator.add_to_cache(x, xts));
-        validator
+    fn new(size: usize) -> Self {
+        Self {
+            size,
+            window: VecDeque::with_capacity(size + 1),
+            counts: HashMap::new(),
+        }
     }
 
-    fn add_to_cache(&mut self, x: i64, xts: Timestamp) {
-        for &(y, yts) in &self.cache {
-            if x == y {
-                continue;
-            }
-            let sum = x + y;
-            let ts1 = xts.min(yts);
-            match self.sums.get(&(x + y)) {
-                None => self.sums.insert(sum, ts1),
-                Some(&ts2) if ts1 > ts2 => self.sums.insert(sum, ts1),
-                Some(_) => continue,
-            };
+    fn is_valid(&self, x: i64) -> bool {
+        if self.window.len() < self.size {
+            return true;
         }
+        self.window.iter().any(|&y| {
+            let z = x - y;
+            z != y && self.counts.get(&z).is_some_and(|&n| n > 0)
+        })
     }
 
-    fn is_valid(&self, x: i64, ts: Timestamp) -> bool {
-        self.sums
-            .get(&x)
-            .map_or(false, |cache_ts| ts - cache_ts <= self.size)
+    // Adds x to the window, returning whether it was valid.
+    fn push(&mut self, x: i64) -> bool {
+        let valid = self.is_valid(x);
+        self.window.push_back(x);
+        *self.counts.entry(x).or_insert(0) += 1;
+        if self.window.len() > self.size {
+            let y = self.window.pop_front().unwrap();
+            *self.counts.get_mut(&y).unwrap() -= 1;
+        }
+        valid
+    }
+}
+
+// Yields the index and value of every invalid number in a stream, reading
+// only as far ahead as needed.
+struct Invalid<I> {
+    nums: std::iter::Enumerate<I>,
+    validator: WindowedValidator,
+}
+
+impl<I: Iterator<Item = i64>> Iterator for Invalid<I> {
+    type Item = (usize, i64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let validator = &mut self.validator;
+        self.nums.find(|&(_, x)| !validator.push(x))
     }
+}
 
-    fn update(&mut self, x: i64, xts: Timestamp) {
-        self.cache.pop_front();
-        self.add_to_cache(x, xts);
-        self.cache.push_back((x, xts));
+fn invalid<I>(nums: I, size: usize) -> Invalid<I::IntoIter>
+where
+    I: IntoIterator<Item = i64>,
+{
+    Invalid {
+        nums: nums.into_iter().enumerate(),
+        validator: WindowedValidator::new(size),
     }
 }
 
-fn find_sum_range(nums: &[i64], target: i64) -> (usize, usize) {
-    let mut sum_per_range: HashMap<(usize, usize), i64> = HashMap::new();
-    let mut range_per_sum: HashMap<i64, (usize, usize)> = HashMap::new();
-    sum_per_range.insert((0, 0), nums[0]);
-    range_per_sum.insert(nums[0], (0, 0));
-    for (i, x) in nums.iter().enumerate().skip(1) {
-        if let Some(&(from, to)) = range_per_sum.get(&target) {
-            if from != to {
-                return (from, to);
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pick {
+    First,
+    Shortest,
+    Longest,
+}
+
+// Calls f with each end index j and the sorted start indices i such that
+// nums[i..=j] sums to target and has at least two numbers, until f returns
+// false. Ranges are found through equal prefix sums, so this works with
+// negative numbers too.
+fn scan_sum_ranges<F>(nums: &[i64], target: i64, mut f: F)
+where
+    F: FnMut(usize, &[usize]) -> bool,
+{
+    let mut prefix = vec![0];
+    prefix.extend(nums.iter().scan(0, |sum, &x| {
+        *sum += x;
+        Some(*sum)
+    }));
+    // starts maps the sum of nums[..i] to each possible start i so far
+    let mut starts: HashMap<i64, Vec<usize>> = HashMap::new();
+    for j in 1..nums.len() {
+        starts.entry(prefix[j - 1]).or_default().push(j - 1);
+        if let Some(is) = starts.get(&(prefix[j + 1] - target)) {
+            if !f(j, is) {
+                return;
             }
         }
-        for from in 0..i {
-            let sum =
-                sum_per_range.get(&(from, i - 1)).unwrap_or(&nums[i - 1]) + x;
-            sum_per_range.insert((from, i), sum);
-            range_per_sum.insert(sum, (from, i));
-        }
     }
-    unreachable!();
+}
+
+// Finds the range of at least two numbers that sums to target and ends
+// first, or is shortest or longest. Ties go to the range that ends first.
+fn find_sum_range(
+    nums: &[i64],
+    target: i64,
+    pick: Pick,
+) -> Option<(usize, usize)> {
+    let mut best: Option<(usize, usize)> = None;
+    let len = |(i, j): (usize, usize)| j - i;
+    scan_sum_ranges(nums, target, |j, is| {
+        let range = match pick {
+            Pick::First | Pick::Longest => (is[0], j),
+            Pick::Shortest => (*is.last().unwrap(), j),
+        };
+        let better = match (pick, best) {
+            (_, None) => true,
+            (Pick::First, Some(_)) => false,
+            (Pick::Shortest, Some(b)) => len(range) < len(b),
+            (Pick::Longest, Some(b)) => len(range) > len(b),
+        };
+        if better {
+            best = Some(range);
+        }
+        pick != Pick::First
+    });
+    best
+}
+
+// Every range of at least two numbers that sums to target, ordered by end
+// and then start.
+fn all_sum_ranges(nums: &[i64], target: i64) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    scan_sum_ranges(nums, target, |j, is| {
+        ranges.extend(is.iter().map(|&i| (i, j)));
+        true
+    });
+    ranges
+}
+
+fn window(arg: Option<&String>) -> usize {
+    arg.map_or(DEFAULT_WINDOW, |s| s.parse().unwrap())
 }
 
 fn main() {
-    let path = std::env::args().nth(1).unwrap();
-    let text = std::fs::read_to_string(&path).unwrap();
-    let nums: Vec<_> = text.lines().map(atoi).collect();
-    let preamble = nums.iter().copied().take(WINDOW_SIZE);
-    let mut validator = WindowedValidator::new(preamble);
-
-    let invalid = *nums.iter().enumerate().skip(WINDOW_SIZE).find(|(ts, x)| {
-        if validator.is_valid(**x, *ts) {
-            validator.update(**x, *ts);
-            false
-        } else {
-            true
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    if args[0] == "stream" {
+        let stdin = std::io::stdin();
+        let nums = stdin.lock().lines().map(|line| atoi(&line.unwrap()));
+        for (i, x) in invalid(nums, window(args.get(1))) {
+            println!("{}: {}", i, x);
+        }
+        return;
+    }
+    if args[0] == "ranges" {
+        let text = std::fs::read_to_string(&args[1]).unwrap();
+        let nums: Vec<_> = text.lines().map(atoi).collect();
+        let target = atoi(&args[2]);
+        let ranges = match args.get(3).map_or("all", |s| s.as_str()) {
+            "all" => all_sum_ranges(&nums, target),
+            mode => {
+                let pick = match mode {
+                    "first" => Pick::First,
+                    "shortest" => Pick::Shortest,
+                    "longest" => Pick::Longest,
+                    _ => panic!("bad mode: {}", mode),
+                };
+                find_sum_range(&nums, target, pick).into_iter().collect()
+            }
+        };
+        for (from, to) in ranges {
+            println!("{}..={}: {:?}", from, to, &nums[from..=to]);
         }
-    }).unwrap().1;
+        return;
+    }
+
+    let text = std::fs::read_to_string(&args[0]).unwrap();
+    let nums: Vec<_> = text.lines().map(atoi).collect();
+    let size = window(args.get(1));
+    let (_, invalid) = invalid(nums.iter().copied(), size)
+        .next()
+        .expect("no invalid number");
     println!("{}", invalid);
 
-    let (from, to) = find_sum_range(&nums, invalid);
+    let (from, to) = find_sum_range(&nums, invalid, Pick::First)
+        .expect("no range sums to the invalid number");
     let min = nums[from..=to].iter().min().unwrap();
     let max = nums[from..=to].iter().max().unwrap();
     println!("{}", min + max);